@@ -0,0 +1,103 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::mirror::{KNOWN_HOSTS, MirrorConfig};
+
+// Adoptium sends Java downloads on to its GitHub releases
+const REDIRECT_HOSTS: [&str; 3] = ["github.com", "objects.githubusercontent.com", "release-assets.githubusercontent.com"];
+
+// Which hosts the launcher is allowed to download from, so that a version spec from anywhere can't make it fetch from anywhere
+// The hosts Mojang and Adoptium use are always allowed, as are hosts the user has set up a mirror for
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct HostAllowlist {
+    // Other hosts that are trusted, ie a mod loader's Maven repository
+    pub extra_hosts: Vec<String>,
+    // Turns the allowlist off, downloads are still upgraded to HTTPS
+    pub allow_any_host: bool,
+}
+
+impl HostAllowlist {
+    pub fn is_allowed(&self, host: &str, mirrors: &MirrorConfig) -> bool {
+        return self.allow_any_host
+            || KNOWN_HOSTS.iter().any(|(known, _)| known.eq_ignore_ascii_case(host))
+            || REDIRECT_HOSTS.iter().any(|known| known.eq_ignore_ascii_case(host))
+            || self.extra_hosts.iter().any(|extra| extra.eq_ignore_ascii_case(host))
+            || mirrors.rules.iter().any(|rule| rule.host.eq_ignore_ascii_case(host));
+    }
+
+    // The URL to download from, upgraded to HTTPS, or why it can't be downloaded from
+    // Mirror URLs never go through this, the user chose those
+    // Only hosts the user allowed can fall back to plain HTTP if the upgrade fails, see allows_http
+    pub fn check(&self, url: &str, mirrors: &MirrorConfig) -> Result<String, String> {
+        let mut parsed = Url::parse(url).map_err(|e| format!("Refusing to download '{0}', it isn't a valid URL: {1}", url, e))?;
+        match parsed.scheme() {
+            "https" => (),
+            "http" => parsed.set_scheme("https").unwrap(),
+            scheme => return Err(format!("Refusing to download {0}, {1} URLs aren't allowed", url, scheme)),
+        }
+        let host = parsed.host_str().unwrap_or_default().to_string();
+        if !self.is_allowed(&host, mirrors) {
+            return Err(format!("Refusing to download {0}, {1} isn't an allowed host. If it's trusted, allow it with 'minelaunch allow-host {1}'", url, host));
+        }
+        return Ok(parsed.to_string());
+    }
+
+    // Whether a plain HTTP URL can be downloaded as is when its host doesn't support HTTPS
+    // Mojang's and Adoptium's hosts all do, so this only applies to hosts the user allowed
+    pub fn allows_http(&self, url: &str) -> bool {
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => return false,
+        };
+        let host = parsed.host_str().unwrap_or_default();
+        let builtin = KNOWN_HOSTS.iter().any(|(known, _)| known.eq_ignore_ascii_case(host))
+            || REDIRECT_HOSTS.iter().any(|known| known.eq_ignore_ascii_case(host));
+        let user_allowed = self.allow_any_host || self.extra_hosts.iter().any(|extra| extra.eq_ignore_ascii_case(host));
+        return parsed.scheme() == "http" && user_allowed && !builtin;
+    }
+
+    // Where a download was redirected is checked as is, since it can't be upgraded at that point
+    pub fn check_redirect(&self, url: &Url, mirrors: &MirrorConfig) -> Result<(), String> {
+        // Mirrors can send downloads anywhere on their own server
+        let origin = url.origin();
+        if mirrors.rules.iter().any(|rule| Url::parse(&rule.url).is_ok_and(|mirror| mirror.origin() == origin)) {
+            return Ok(());
+        }
+        let host = url.host_str().unwrap_or_default();
+        if url.scheme() != "https" {
+            return Err(format!("Refusing to follow a redirect to {0}, only HTTPS is allowed", url));
+        }
+        if !self.is_allowed(host, mirrors) {
+            return Err(format!("Refusing to follow a redirect to {0}, {1} isn't an allowed host. If it's trusted, allow it with 'minelaunch allow-host {1}'", url, host));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_upgrades_to_https_and_refuses_unknown_hosts() {
+        let allowlist = HostAllowlist::default();
+        let mirrors = MirrorConfig::default();
+        assert_eq!(allowlist.check("http://resources.download.minecraft.net/ab/abcdef", &mirrors),
+                   Ok(String::from("https://resources.download.minecraft.net/ab/abcdef")));
+        assert!(allowlist.check("https://example.com/a.jar", &mirrors).is_err());
+        assert!(allowlist.check("ftp://libraries.minecraft.net/a.jar", &mirrors).is_err());
+    }
+
+    #[test]
+    fn allows_http_only_for_user_allowed_hosts() {
+        let allowlist = HostAllowlist {
+            extra_hosts: vec![String::from("maven.example.com")],
+            allow_any_host: false,
+        };
+        assert!(allowlist.allows_http("http://maven.example.com/a.jar"));
+        assert!(!allowlist.allows_http("https://maven.example.com/a.jar"));
+        assert!(!allowlist.allows_http("http://resources.download.minecraft.net/ab/abcdef"));
+        assert!(!allowlist.allows_http("http://other.example.com/a.jar"));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_std::task;
+use futures::stream::StreamExt;
+use tempfile::tempdir;
+
+use crate::hash_cache::HashCache;
+use crate::pool::{map_parallel, thread_count};
+use crate::util::sha1_hex;
+
+// Measures how fast files are verified, over a synthetic asset tree laid out like "assets/objects/"
+// Compares hashing one file at a time with hashing on the thread pool, then checking against a warm hash cache
+// The files were just written so they're likely in the page cache, making this a best case for the disk
+pub fn verify_throughput(file_count: usize, file_size: usize) -> Result<(), String> {
+    let root = tempdir().map_err(|e| format!("Failed to create a temporary directory: {0}", e))?;
+    let root_path = root.path().to_str().unwrap().to_string();
+
+    println!("Writing {0} files of {1} bytes to {2}", file_count, file_size, root_path);
+    let mut files = Vec::new();
+    for i in 0..file_count {
+        // Vary the contents so every file has its own hash, like real assets
+        let mut data = vec![0u8; file_size];
+        for (j, byte) in data.iter_mut().enumerate() {
+            *byte = (i.wrapping_mul(31).wrapping_add(j) % 251) as u8;
+        }
+        let sha1 = sha1_hex(&data);
+        let path = PathBuf::from(format!("{0}/objects/{1}/{2}", root_path, &sha1[..2], sha1));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &data).map_err(|e| format!("Failed to write {0}: {1}", path.display(), e))?;
+        files.push((path, sha1));
+    }
+    let total_bytes = (file_count * file_size) as f64;
+
+    // Deep verify so every file gets hashed, this is what a cold cache costs
+    let hashes = HashCache::open(&root_path, true);
+    let start = Instant::now();
+    let mut valid = 0;
+    for (path, sha1) in files.iter() {
+        if hashes.check_file(path, sha1, file_size as u64) {
+            valid += 1;
+        }
+    }
+    report("Sequential", start.elapsed(), valid, file_count, total_bytes);
+
+    let hashes = Arc::new(HashCache::open(&root_path, true));
+    let valid = verify_parallel(&files, &hashes, file_size as u64, "Parallel", total_bytes);
+    hashes.save();
+
+    // Unchanged files only need their metadata read once the cache knows them
+    let hashes = Arc::new(HashCache::open(&root_path, false));
+    let cached_valid = verify_parallel(&files, &hashes, file_size as u64, "Warm cache", total_bytes);
+
+    if valid != file_count || cached_valid != file_count {
+        return Err(String::from("Some files failed to verify"));
+    }
+    return Ok(());
+}
+
+fn verify_parallel(files: &[(PathBuf, String)], hashes: &Arc<HashCache>, size: u64, name: &str, total_bytes: f64) -> usize {
+    let hashes = hashes.clone();
+    let start = Instant::now();
+    let verified = map_parallel(files.to_vec(), move |(path, sha1)| Ok(hashes.check_file(&path, &sha1, size)));
+    let valid = task::block_on(verified.filter(|valid| futures::future::ready(*valid == Ok(true))).count());
+    report(&format!("{0} ({1} threads)", name, thread_count()), start.elapsed(), valid, files.len(), total_bytes);
+    return valid;
+}
+
+fn report(name: &str, elapsed: Duration, valid: usize, file_count: usize, total_bytes: f64) {
+    let seconds = elapsed.as_secs_f64();
+    println!("{0}: {1}/{2} valid in {3:.3}s, {4:.0} files/s, {5:.1} MB/s",
+             name, valid, file_count, seconds, file_count as f64 / seconds, total_bytes / seconds / 1_000_000.0);
+}
+
+// Not a real test, run it with "cargo test --release bench_verify -- --ignored --nocapture"
+// Around the number of assets in a modern version by default, which are mostly small
+// BENCH_FILES and BENCH_SIZE change how many files there are and how big they are
+#[test]
+#[ignore]
+fn bench_verify() {
+    let file_count = std::env::var("BENCH_FILES").map_or(4000, |c| c.parse().unwrap());
+    let file_size = std::env::var("BENCH_SIZE").map_or(16384, |s| s.parse().unwrap());
+    verify_throughput(file_count, file_size).unwrap();
+}
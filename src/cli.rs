@@ -0,0 +1,534 @@
+use std::sync::Arc;
+use async_std::task;
+
+use crate::{launcher_environment, launcher_path};
+use crate::config::LauncherConfig;
+use crate::env::{EnvLayer, LayeredEnvironment};
+use crate::instance::Instance;
+use crate::launch_mode::{LaunchModes, DEFAULT_JDWP_PORT};
+use crate::log::SessionLog;
+use crate::minecraft::{check_version_compatibility, deobfuscate_file, dry_launch_minecraft_version, get_version_list, launch_minecraft_version};
+use crate::mirror::KNOWN_HOSTS;
+use crate::process::ProcessRegistry;
+use crate::proxy::PASSWORD_VARIABLE;
+use crate::prune::prune_unused;
+use crate::repair::{VerifyReport, repair_version, verify_version};
+use crate::spec::MinecraftVersion;
+
+const USAGE: &str = "Usage: minelaunch [command]
+
+Runs the launcher GUI if no command is given.
+
+Commands:
+    deobf <version> <file>                       Write a deobfuscated copy of a crash report or log
+    launch <version> [instance] [user]           Launch a version and wait for it to exit
+    dry-launch <version> [instance] [user]       Prepare a version and write {instance}/launch.sh (launch.bat on Windows)
+                                                 instead of launching, then print the launch command as JSON
+    compat <version>                             Report anything in an installed version's spec that isn't supported
+    verify <version> [instance] [--repair] [--deep]
+                                                 Check an installed version for missing, corrupt and extraneous files,
+                                                 optionally downloading or removing them, --deep hashes every file
+    env [instance]                               List the launcher variables for an instance and where they come from
+    prune [--dry-run] [--versions]               Remove libraries and assets that no installed version uses,
+                                                 --versions also removes versions that aren't pinned or used by an instance,
+                                                 instances last launched by older launchers don't say which version they use
+    pin <version>                                Keep a version from being removed by prune
+    unpin <version>                              Let prune remove a version again
+    mirror                                       List the hosts downloads come from and their mirrors
+    mirror <host> <url>                          Download everything from a host through a mirror instead
+    mirror <host> --remove                       Download from a host directly again
+    mirror --fallback <on|off>                   Whether to use the original host when a mirror fails
+    allow-host <host>                            Allow downloads from a host besides Mojang's, Adoptium's and the mirrors,
+                                                 falling back to plain HTTP for it when HTTPS fails
+    disallow-host <host>                         Stop allowing downloads from a host again
+    proxy [<url>|--off]                          Show or set the proxy downloads go through, ie http://proxy:8080 or
+                                                 socks5://127.0.0.1:1080, the login and other network settings are in
+                                                 launcher.json, set MINELAUNCH_PROXY_PASSWORD to keep the password out
+                                                 of it since it's saved in plain text
+    help                                         Show this message
+
+<version> can also be \"release\" or \"snapshot\" for the latest one.
+
+Launch options:
+    --jdwp [port]    Start a debugger agent, on port 5005 by default
+    --suspend        Wait for a debugger to attach before starting the game
+    --jfr            Record with Java Flight Recorder, saved to {instance}/debug/ on exit
+    --gc-log         Log garbage collection to {instance}/debug/";
+
+// Runs a command given on the command line, returning the exit code
+pub fn run(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "deobf" => deobf(&args[1..]),
+        "launch" => launch(&args[1..]),
+        "dry-launch" => dry_launch(&args[1..]),
+        "env" => env(&args[1..]),
+        "compat" => compat(&args[1..]),
+        "verify" => verify(&args[1..]),
+        "prune" => prune(&args[1..]),
+        "pin" => pin(&args[1..], true),
+        "unpin" => pin(&args[1..], false),
+        "mirror" => mirror(&args[1..]),
+        "allow-host" => allow_host(&args[1..], true),
+        "disallow-host" => allow_host(&args[1..], false),
+        "proxy" => proxy(&args[1..]),
+        "help" | "--help" | "-h" => {
+            println!("{0}", USAGE);
+            0
+        },
+        command => {
+            eprintln!("Unknown command '{0}'\n\n{1}", command, USAGE);
+            2
+        },
+    }
+}
+
+fn deobf(args: &[String]) -> i32 {
+    if args.len() != 2 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+
+    match task::block_on(deobfuscate_file(launcher_path(), args[0].clone(), args[1].clone())) {
+        Ok(path) => {
+            println!("Deobfuscated {0} to {1}", args[1], path);
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+// Splits launch options like "--jfr" from the positional arguments
+fn parse_launch_options(args: &[String]) -> Result<(Vec<String>, LaunchModes), String> {
+    let mut positional = Vec::new();
+    let mut modes = LaunchModes::default();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jdwp" => {
+                // The port is optional
+                let mut port = DEFAULT_JDWP_PORT;
+                if let Some(p) = args.peek().and_then(|p| p.parse().ok()) {
+                    port = p;
+                    args.next();
+                }
+                modes.jdwp_port = Some(port);
+            },
+            "--suspend" => modes.jdwp_suspend = true,
+            "--jfr" => modes.flight_recorder = true,
+            "--gc-log" => modes.gc_logging = true,
+            option if option.starts_with("--") => return Err(format!("Unknown option '{0}'", option)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if modes.jdwp_suspend && modes.jdwp_port.is_none() {
+        return Err(String::from("--suspend needs --jdwp"));
+    }
+    return Ok((positional, modes));
+}
+
+// Looks up the version and instance for a launch, along with the environment to launch with
+fn launch_target(args: &[String]) -> Result<(MinecraftVersion, Instance, LayeredEnvironment), String> {
+    let instance_name = args.get(1).map_or("default", |i| i.as_str());
+    if !Instance::is_valid_name(instance_name) {
+        return Err(format!("'{0}' isn't a valid instance name", instance_name));
+    }
+
+    let versions = task::block_on(get_version_list(&launcher_path()))?;
+    let version_id = match args[0].as_str() {
+        "release" => &versions.latest.release,
+        "snapshot" => &versions.latest.snapshot,
+        id => id,
+    };
+    let version = match versions.versions.iter().find(|v| v.id == version_id) {
+        Some(version) => version.clone(),
+        None => return Err(format!("Unknown version '{0}'", version_id)),
+    };
+
+    let mut env = launcher_environment(&launcher_path());
+    if let Some(username) = args.get(2) {
+        env.layer_mut(EnvLayer::Account).set("auth_player_name", username);
+    }
+    return Ok((version, Instance::load(&launcher_path(), instance_name)?, env));
+}
+
+fn launch(args: &[String]) -> i32 {
+    let (args, modes) = match parse_launch_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{0}\n\n{1}", e, USAGE);
+            return 2;
+        },
+    };
+    if args.is_empty() || args.len() > 3 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+    let (version, instance, env) = match launch_target(&args) {
+        Ok(target) => target,
+        Err(e) => {
+            eprintln!("{0}", e);
+            return 1;
+        },
+    };
+
+    let log = Arc::new(SessionLog::new(Some(version.id.clone())));
+    let processes = Arc::new(ProcessRegistry::new());
+    match task::block_on(launch_minecraft_version(launcher_path(), version, instance, modes, Box::new(env), log, processes)) {
+        Ok(game_exit) => {
+            if let Some(log_path) = &game_exit.log_path {
+                println!("Log saved to {0}", log_path);
+            }
+            if let Some(crash) = &game_exit.crash {
+                println!("{0}", crash.summary());
+            }
+            match game_exit.status.success() {
+                true => 0,
+                false => 1,
+            }
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn dry_launch(args: &[String]) -> i32 {
+    let (args, modes) = match parse_launch_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{0}\n\n{1}", e, USAGE);
+            return 2;
+        },
+    };
+    if args.is_empty() || args.len() > 3 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+    let (version, instance, env) = match launch_target(&args) {
+        Ok(target) => target,
+        Err(e) => {
+            eprintln!("{0}", e);
+            return 1;
+        },
+    };
+
+    match task::block_on(dry_launch_minecraft_version(launcher_path(), version, instance, modes, Box::new(env))) {
+        Ok(dry_launch) => {
+            println!("{0}", dry_launch.command.to_json());
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn env(args: &[String]) -> i32 {
+    if args.len() > 1 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+    let instance_name = args.first().map_or("default", |i| i.as_str());
+    if !Instance::is_valid_name(instance_name) {
+        eprintln!("'{0}' isn't a valid instance name", instance_name);
+        return 2;
+    }
+
+    let minecraft_path = launcher_path();
+    let mut env = launcher_environment(&minecraft_path);
+    match Instance::load(&minecraft_path, instance_name).and_then(|instance| instance.load_environment()) {
+        Ok(instance_env) => env.set_layer(EnvLayer::Instance, instance_env),
+        Err(e) => {
+            eprintln!("{0}", e);
+            return 1;
+        },
+    }
+    for variable in env.describe() {
+        println!("{0}", variable);
+    }
+    0
+}
+
+fn compat(args: &[String]) -> i32 {
+    if args.len() != 1 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+
+    match check_version_compatibility(&launcher_path(), &args[0]) {
+        Ok(report) => {
+            if report.errors.is_empty() && report.warnings.is_empty() {
+                println!("Minecraft {0} is fully supported", args[0]);
+            }
+            else {
+                println!("{0}", report.summary());
+            }
+            match report.is_compatible() {
+                true => 0,
+                false => 1,
+            }
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn verify(args: &[String]) -> i32 {
+    let mut repair = false;
+    let mut deep_verify = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--repair" => repair = true,
+            "--deep" => deep_verify = true,
+            option if option.starts_with("--") => {
+                eprintln!("Unknown option '{0}'\n\n{1}", option, USAGE);
+                return 2;
+            },
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.is_empty() || positional.len() > 2 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+    let (version, instance, _) = match launch_target(&positional) {
+        Ok(target) => target,
+        Err(e) => {
+            eprintln!("{0}", e);
+            return 1;
+        },
+    };
+    // Only check an instance's own files when one is asked for
+    let instance = if positional.len() == 2 { Some(instance) } else { None };
+
+    let print_report = |report: &VerifyReport| {
+        for line in report.lines() {
+            println!("{0}", line);
+        }
+        println!("{0}", report.summary());
+    };
+    let report = if repair {
+        match task::block_on(repair_version(launcher_path(), version, instance, deep_verify)) {
+            Ok((before, after)) => {
+                println!("Before repairing:");
+                print_report(&before);
+                println!("After repairing:");
+                after
+            },
+            Err(e) => {
+                eprintln!("{0}", e);
+                return 1;
+            },
+        }
+    }
+    else {
+        match task::block_on(verify_version(launcher_path(), version, instance, deep_verify)) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("{0}", e);
+                return 1;
+            },
+        }
+    };
+    print_report(&report);
+    match report.is_ok() {
+        true => 0,
+        false => 1,
+    }
+}
+
+fn prune(args: &[String]) -> i32 {
+    let mut dry_run = false;
+    let mut remove_versions = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--versions" => remove_versions = true,
+            _ => {
+                eprintln!("Unknown option '{0}'\n\n{1}", arg, USAGE);
+                return 2;
+            },
+        }
+    }
+
+    let report = prune_unused(&launcher_path(), remove_versions, dry_run);
+    for id in report.versions.iter() {
+        println!("Minecraft {0}", id);
+    }
+    for file in report.files.iter() {
+        println!("{0}", file);
+    }
+    for warning in report.warnings.iter() {
+        println!("Warning: {0}", warning);
+    }
+    println!("{0}", report.summary());
+    return 0;
+}
+
+fn pin(args: &[String], pinned: bool) -> i32 {
+    if args.len() != 1 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+
+    let mut config = LauncherConfig::load(&launcher_path());
+    config.pinned_versions.retain(|id| *id != args[0]);
+    if pinned {
+        config.pinned_versions.push(args[0].clone());
+    }
+    match config.save(&launcher_path()) {
+        Ok(()) => {
+            println!("Minecraft {0} {1}", args[0], if pinned { "pinned" } else { "unpinned" });
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn mirror(args: &[String]) -> i32 {
+    let mut config = LauncherConfig::load(&launcher_path());
+    let message = match args {
+        [] => {
+            for (host, contents) in KNOWN_HOSTS {
+                println!("{0} ({1})", host, contents);
+                if let Some(rule) = config.mirrors.rules.iter().find(|rule| rule.host == host) {
+                    println!("    mirrored by {0}", rule.url);
+                }
+            }
+            // Rules can be added for any host, ie ones modded specs download from
+            for rule in config.mirrors.rules.iter().filter(|rule| !KNOWN_HOSTS.iter().any(|(host, _)| *host == rule.host)) {
+                println!("{0}\n    mirrored by {1}", rule.host, rule.url);
+            }
+            println!("Falling back to the original host is {0}", if config.mirrors.fallback_to_origin { "on" } else { "off" });
+            return 0;
+        },
+        [flag, value] if flag == "--fallback" => {
+            config.mirrors.fallback_to_origin = match value.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    eprintln!("{0}", USAGE);
+                    return 2;
+                },
+            };
+            format!("Falling back to the original host turned {0}", value)
+        },
+        [host, flag] if flag == "--remove" => {
+            if !config.mirrors.remove(host) {
+                eprintln!("{0} isn't mirrored", host);
+                return 1;
+            }
+            format!("{0} is no longer mirrored", host)
+        },
+        [host, url] => {
+            if let Err(e) = config.mirrors.set(host, url) {
+                eprintln!("{0}", e);
+                return 1;
+            }
+            format!("{0} is now mirrored by {1}", host, url)
+        },
+        _ => {
+            eprintln!("{0}", USAGE);
+            return 2;
+        },
+    };
+
+    match config.save(&launcher_path()) {
+        Ok(()) => {
+            println!("{0}", message);
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn allow_host(args: &[String], allowed: bool) -> i32 {
+    if args.len() != 1 {
+        eprintln!("{0}", USAGE);
+        return 2;
+    }
+
+    let host = args[0].to_lowercase();
+    let mut config = LauncherConfig::load(&launcher_path());
+    config.allowlist.extra_hosts.retain(|h| *h != host);
+    if allowed {
+        config.allowlist.extra_hosts.push(host.clone());
+    }
+    match config.save(&launcher_path()) {
+        Ok(()) => {
+            println!("Downloads from {0} are {1}", host, if allowed { "allowed" } else { "no longer allowed" });
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
+
+fn proxy(args: &[String]) -> i32 {
+    let mut config = LauncherConfig::load(&launcher_path());
+    let message = match args {
+        [] => {
+            if !config.proxy.is_enabled() {
+                println!("No proxy is set");
+                return 0;
+            }
+            println!("Downloads go through {0}{1}", config.proxy.url, if config.proxy.username.is_empty() { "" } else { " with a login" });
+            if !config.proxy.no_proxy.is_empty() {
+                println!("Except for {0}", config.proxy.no_proxy.join(", "));
+            }
+            if !config.proxy.password.is_empty() {
+                println!("The password is saved in plain text in launcher.json, set {0} instead to keep it out", PASSWORD_VARIABLE);
+            }
+            if config.proxy.java_properties && !config.proxy.password().is_empty() {
+                println!("The game uses it too, but without the password");
+            }
+            else if config.proxy.java_properties {
+                println!("The game uses it too");
+            }
+            return 0;
+        },
+        [flag] if flag == "--off" => {
+            config.proxy.url = String::new();
+            String::from("Downloads no longer go through a proxy")
+        },
+        [url] => {
+            config.proxy.url = url.clone();
+            if let Err(e) = config.proxy.build() {
+                eprintln!("{0}", e);
+                return 1;
+            }
+            format!("Downloads now go through {0}", url)
+        },
+        _ => {
+            eprintln!("{0}", USAGE);
+            return 2;
+        },
+    };
+
+    match config.save(&launcher_path()) {
+        Ok(()) => {
+            println!("{0}", message);
+            0
+        },
+        Err(e) => {
+            eprintln!("{0}", e);
+            1
+        },
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::allowlist::HostAllowlist;
+use crate::link::AssetLinkMode;
+use crate::mirror::MirrorConfig;
+use crate::proxy::ProxyConfig;
+
+// Settings for the launcher itself, stored in "{launcher}/launcher.json"
+// Every field has a default so that older config files still load
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct LauncherConfig {
+    // How assets get put into "assets/virtual/" and "resources/" for older versions
+    pub asset_links: AssetLinkMode,
+    // Versions that pruning never removes
+    pub pinned_versions: Vec<String>,
+    // Where to download from instead of Mojang's and Adoptium's hosts
+    pub mirrors: MirrorConfig,
+    // Hosts downloads are allowed from besides Mojang's, Adoptium's and the mirrors
+    pub allowlist: HostAllowlist,
+    pub proxy: ProxyConfig,
+    // PEM files with extra certificates to trust, ie for a proxy that inspects HTTPS
+    pub ca_certificates: Vec<String>,
+    // In seconds, 0 waits as long as it takes
+    pub connect_timeout: u64,
+    // How long a whole download can take in seconds, 0 waits as long as it takes
+    pub download_timeout: u64,
+}
+
+impl LauncherConfig {
+    fn path(minecraft_path: &str) -> String {
+        return format!("{0}/launcher.json", minecraft_path);
+    }
+
+    // Falls back to the defaults if the config hasn't been saved yet or can't be read
+    pub fn load(minecraft_path: &str) -> LauncherConfig {
+        let config_path = LauncherConfig::path(minecraft_path);
+        let config_json = match fs::read_to_string(&config_path) {
+            Ok(config_json) => config_json,
+            Err(_) => return LauncherConfig::default(),
+        };
+        match serde_json::from_str(&config_json) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Failed to parse {0}, using the default settings: {1}", config_path, e);
+                LauncherConfig::default()
+            },
+        }
+    }
+
+    pub fn save(&self, minecraft_path: &str) -> Result<(), String> {
+        let config_path = LauncherConfig::path(minecraft_path);
+        let config_json = serde_json::to_string_pretty(self).unwrap();
+        return fs::write(&config_path, config_json).map_err(|e| format!("Failed to write {0}: {1}", config_path, e));
+    }
+}
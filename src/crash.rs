@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::fs;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrashKind {
+    // A report written by the game into crash-reports/
+    CrashReport,
+    // A fatal error log written by the JVM itself, ie hs_err_pid1234.log
+    JvmError,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub kind: CrashKind,
+    pub path: String,
+    pub description: Option<String>,
+    pub exception: Option<String>,
+    pub suspected_mods: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn parse(kind: CrashKind, path: &str) -> CrashReport {
+        // Crash reports are written by the game so they might not be valid UTF-8
+        let contents = fs::read(path).unwrap_or_default();
+        let contents = String::from_utf8_lossy(&contents);
+        let mut report = CrashReport {
+            kind: kind.clone(),
+            path: path.to_string(),
+            description: None,
+            exception: None,
+            suspected_mods: Vec::new(),
+        };
+        match kind {
+            CrashKind::CrashReport => report.parse_crash_report(&contents),
+            CrashKind::JvmError => report.parse_jvm_error(&contents),
+        }
+        return report;
+    }
+
+    fn parse_crash_report(&mut self, contents: &str) {
+        let mut lines = contents.lines().peekable();
+        while let Some(line) = lines.next() {
+            if let Some(description) = line.strip_prefix("Description: ") {
+                self.description = Some(description.trim().to_string());
+
+                // The exception follows the description after a blank line
+                while lines.peek().is_some_and(|l| l.trim().is_empty()) {
+                    lines.next();
+                }
+                self.exception = lines.next().map(|l| l.trim().to_string());
+            }
+            // Forge lists the mods it thinks caused the crash, either inline or on the following indented lines
+            else if line.starts_with("Suspected Mod") && self.suspected_mods.is_empty() {
+                let inline_mods = line.split_once(':').map_or("", |(_, mods)| mods.trim());
+                if !inline_mods.is_empty() && inline_mods != "NONE" {
+                    self.suspected_mods.extend(inline_mods.split(", ").map(|m| m.to_string()));
+                }
+                while lines.peek().is_some_and(|l| l.starts_with('\t') || l.starts_with("  ")) {
+                    self.suspected_mods.push(lines.next().unwrap().trim().to_string());
+                }
+            }
+        }
+    }
+
+    fn parse_jvm_error(&mut self, contents: &str) {
+        self.description = Some(String::from("The Java Runtime Environment crashed"));
+        let mut lines = contents.lines();
+        while let Some(line) = lines.next() {
+            // The signal or exception comes right after the header, ie "#  SIGSEGV (0xb) at pc=..."
+            if line.starts_with("# A fatal error has been detected") {
+                self.exception = lines.find(|l| !l.trim_start_matches('#').trim().is_empty())
+                    .map(|l| l.trim_start_matches('#').trim().to_string());
+            }
+            else if line.starts_with("# Problematic frame:") {
+                if let Some(frame) = lines.next() {
+                    self.description = Some(format!("Problematic frame: {0}", frame.trim_start_matches('#').trim()));
+                }
+            }
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = match &self.description {
+            Some(description) => description.clone(),
+            None => String::from("Unknown crash"),
+        };
+        if let Some(exception) = &self.exception {
+            summary += &format!("\n{0}", exception);
+        }
+        if !self.suspected_mods.is_empty() {
+            summary += &format!("\nSuspected mods: {0}", self.suspected_mods.join(", "));
+        }
+        return summary;
+    }
+}
+
+// Looks for crash reports and JVM error logs written since the game started
+// Crash reports from the game are preferred since they're more informative
+pub fn find_crash_report(game_directory: &str, pid: u32, since: SystemTime) -> Option<CrashReport> {
+    let crash_reports_path = format!("{0}/crash-reports/", game_directory);
+    if Path::new(&crash_reports_path).exists() {
+        let mut newest_report = None;
+        for entry in fs::read_dir(&crash_reports_path).unwrap() {
+            let entry = entry.unwrap();
+            let modified = entry.metadata().unwrap().modified().unwrap();
+            if modified >= since && newest_report.as_ref().is_none_or(|(t, _)| modified > *t) {
+                newest_report = Some((modified, entry.path()));
+            }
+        }
+        if let Some((_, path)) = newest_report {
+            return Some(CrashReport::parse(CrashKind::CrashReport, path.to_str().unwrap()));
+        }
+    }
+
+    // The JVM writes its error log to the working directory, which is the game directory
+    let jvm_error_path = format!("{0}/hs_err_pid{1}.log", game_directory, pid);
+    if Path::new(&jvm_error_path).exists() {
+        return Some(CrashReport::parse(CrashKind::JvmError, &jvm_error_path));
+    }
+    return None;
+}
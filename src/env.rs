@@ -1,22 +1,90 @@
-use std::collections::BTreeMap;
-use regex::Regex;
-use regex::Captures;
-use lazy_static::lazy_static;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
 
-lazy_static! {
-    static ref REPLACEMENT_REGEX: Regex = Regex::new(r"\$\{([^\}]*)\}").unwrap();
+// Variables that couldn't be resolved, collected so they can all be reported at once
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveError {
+    // Variables that aren't defined and have no default
+    pub missing: Vec<String>,
+    // Chains of variables that refer back to themselves, ie "a -> b -> a"
+    pub cycles: Vec<String>,
 }
 
-#[derive(Clone)]
+impl ResolveError {
+    pub fn is_empty(&self) -> bool {
+        return self.missing.is_empty() && self.cycles.is_empty();
+    }
+
+    pub fn extend(&mut self, other: ResolveError) {
+        for name in other.missing {
+            if !self.missing.contains(&name) {
+                self.missing.push(name);
+            }
+        }
+        for cycle in other.cycles {
+            if !self.cycles.contains(&cycle) {
+                self.cycles.push(cycle);
+            }
+        }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            problems.push(format!("undefined variables {0}", self.missing.join(", ")));
+        }
+        if !self.cycles.is_empty() {
+            problems.push(format!("variables that refer to themselves {0}", self.cycles.join(", ")));
+        }
+        write!(f, "{0}", problems.join("; "))
+    }
+}
+
+// Variables for substituting into strings like "--gameDir ${game_directory}"
+// Supports "${name:-default}" for when a variable is undefined or empty, and "$${" for a literal "${"
+// Variable values can refer to other variables, unless they were set as literals
+// Stored as a TOML table of strings, ie 'version_type = "Modded"'
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
 pub struct Environment {
     map: BTreeMap<String, String>,
+    // Variables whose values are used as they are, ie paths, which can contain "${" themselves
+    #[serde(skip)]
+    literals: BTreeSet<String>,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
             map: BTreeMap::new(),
+            literals: BTreeSet::new(),
+        }
+    }
+
+    pub fn from_toml(toml_string: &str) -> Result<Environment, String> {
+        return toml::from_str(toml_string).map_err(|e| e.to_string());
+    }
+
+    pub fn to_toml(&self) -> String {
+        return toml::to_string(self).unwrap();
+    }
+
+    // Loads variables from a TOML file, which doesn't need to exist
+    pub fn load(path: &str) -> Result<Environment, String> {
+        if !Path::new(path).exists() {
+            return Ok(Environment::new());
         }
+        let toml_string = fs::read_to_string(path).map_err(|e| format!("Failed to read {0}: {1}", path, e))?;
+        return Environment::from_toml(&toml_string).map_err(|e| format!("Failed to parse {0}: {1}", path, e));
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        return fs::write(path, self.to_toml()).map_err(|e| format!("Failed to write {0}: {1}", path, e));
     }
 
     pub fn get(&self, variable: &str) -> Option<&String> {
@@ -25,21 +93,294 @@ impl Environment {
 
     pub fn set(&mut self, variable: &str, value: &str) {
         self.map.insert(String::from(variable), String::from(value));
+        self.literals.remove(variable);
+    }
+
+    // Sets a variable whose value doesn't get resolved
+    pub fn set_literal(&mut self, variable: &str, value: &str) {
+        self.map.insert(String::from(variable), String::from(value));
+        self.literals.insert(String::from(variable));
+    }
+
+    pub fn is_literal(&self, variable: &str) -> bool {
+        return self.literals.contains(variable);
     }
 
-    pub fn remove(&mut self, variable: &str) {
-        self.map.remove(variable);
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        return self.map.iter();
     }
 
+    // Resolves variables, replacing any that can't be resolved with an empty string
     pub fn resolve(&self, fmt_string: &str) -> String {
-        return REPLACEMENT_REGEX.replace_all(fmt_string, |captures: &Captures| {
-            match self.map.get(&captures[1]) {
-                Some(s) => s,
-                None => {
-                    println!("Need to define '{0}'", &captures[1]);
-                    ""
-                },
+        let mut errors = ResolveError::default();
+        let resolved = self.resolve_with(fmt_string, &mut Vec::new(), &mut errors);
+        if !errors.is_empty() {
+            println!("Couldn't resolve '{0}': {1}", fmt_string, errors);
+        }
+        return resolved;
+    }
+
+    // Resolves variables, failing with every variable that couldn't be resolved
+    pub fn resolve_strict(&self, fmt_string: &str) -> Result<String, ResolveError> {
+        let mut errors = ResolveError::default();
+        let resolved = self.resolve_with(fmt_string, &mut Vec::new(), &mut errors);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        return Ok(resolved);
+    }
+
+    // The stack holds the variables currently being resolved, to catch cycles
+    fn resolve_with(&self, fmt_string: &str, stack: &mut Vec<String>, errors: &mut ResolveError) -> String {
+        let mut resolved = String::new();
+        let mut rest = fmt_string;
+        while let Some(start) = rest.find('$') {
+            resolved += &rest[..start];
+            rest = &rest[start..];
+            if let Some(after) = rest.strip_prefix("$${") {
+                resolved += "${";
+                rest = after;
+                continue;
+            }
+            if !rest.starts_with("${") {
+                resolved += "$";
+                rest = &rest[1..];
+                continue;
+            }
+
+            // Defaults can contain variables themselves, so find the matching brace
+            let end = match matching_brace(&rest[2..]) {
+                Some(end) => end + 2,
+                None => break, // Unterminated, so leave the rest as it is
+            };
+            let expression = &rest[2..end];
+            rest = &rest[end + 1..];
+            let (name, default) = match expression.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expression, None),
+            };
+            resolved += &self.resolve_variable(name, default, stack, errors);
+        }
+        resolved += rest;
+        return resolved;
+    }
+
+    fn resolve_variable(&self, name: &str, default: Option<&str>, stack: &mut Vec<String>, errors: &mut ResolveError) -> String {
+        if let Some(position) = stack.iter().position(|n| n == name) {
+            let mut cycle = stack[position..].to_vec();
+            cycle.push(name.to_string());
+            errors.extend(ResolveError {
+                missing: Vec::new(),
+                cycles: vec![cycle.join(" -> ")],
+            });
+            return String::new();
+        }
+
+        // Like the shell, the default is also used when the variable is empty
+        let value = match (self.map.get(name), default) {
+            (Some(value), None) => value,
+            (Some(value), Some(_)) if !value.is_empty() => value,
+            (_, Some(default)) => return self.resolve_with(default, stack, errors),
+            (None, None) => {
+                errors.extend(ResolveError {
+                    missing: vec![name.to_string()],
+                    cycles: Vec::new(),
+                });
+                return String::new();
+            },
+        };
+        if self.is_literal(name) {
+            return value.clone();
+        }
+        stack.push(name.to_string());
+        let resolved = self.resolve_with(value, stack, errors);
+        stack.pop();
+        return resolved;
+    }
+}
+
+// Where a variable came from, from lowest to highest precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EnvLayer {
+    // Built into the launcher
+    Defaults,
+    // "{launcher}/environment.toml"
+    Global,
+    // "{instance}/environment.toml"
+    Instance,
+    // The player's details, saved to "{launcher}/account.toml"
+    Account,
+    // Set while launching, ie ${classpath} and ${game_directory}
+    Launch,
+}
+
+impl fmt::Display for EnvLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            EnvLayer::Defaults => "defaults",
+            EnvLayer::Global => "global",
+            EnvLayer::Instance => "instance",
+            EnvLayer::Account => "account",
+            EnvLayer::Launch => "launch",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Environments stacked on top of each other, where higher layers override lower ones
+#[derive(Debug, Clone, Default)]
+pub struct LayeredEnvironment {
+    layers: BTreeMap<EnvLayer, Environment>,
+}
+
+impl LayeredEnvironment {
+    pub fn new() -> LayeredEnvironment {
+        LayeredEnvironment {
+            layers: BTreeMap::new(),
+        }
+    }
+
+    pub fn layer(&self, layer: EnvLayer) -> Option<&Environment> {
+        return self.layers.get(&layer);
+    }
+
+    pub fn layer_mut(&mut self, layer: EnvLayer) -> &mut Environment {
+        return self.layers.entry(layer).or_default();
+    }
+
+    pub fn set_layer(&mut self, layer: EnvLayer, env: Environment) {
+        self.layers.insert(layer, env);
+    }
+
+    // Sets a variable in the launch layer, since everything else comes from a config
+    // These come from the launcher rather than a template, so they're used as they are
+    pub fn set(&mut self, variable: &str, value: &str) {
+        self.layer_mut(EnvLayer::Launch).set_literal(variable, value);
+    }
+
+    // Merges the layers into one environment, with variables from higher layers winning
+    pub fn flatten(&self) -> Environment {
+        let mut flattened = Environment::new();
+        for env in self.layers.values() {
+            for (name, value) in env.iter() {
+                if env.is_literal(name) {
+                    flattened.set_literal(name, value);
+                }
+                else {
+                    flattened.set(name, value);
+                }
+            }
+        }
+        return flattened;
+    }
+
+    pub fn resolve_strict(&self, fmt_string: &str) -> Result<String, ResolveError> {
+        return self.flatten().resolve_strict(fmt_string);
+    }
+
+    // Every variable with its value and the layer it came from, sorted by name
+    pub fn sources(&self) -> Vec<(String, String, EnvLayer)> {
+        let mut sources: BTreeMap<String, (String, EnvLayer)> = BTreeMap::new();
+        for (layer, env) in self.layers.iter() {
+            for (name, value) in env.iter() {
+                sources.insert(name.clone(), (value.clone(), *layer));
+            }
+        }
+        return sources.into_iter().map(|(name, (value, layer))| (name, value, layer)).collect();
+    }
+
+    // Lines like "version_type = Modded (instance)" for seeing where each variable came from
+    pub fn describe(&self) -> Vec<String> {
+        return self.sources().into_iter().map(|(name, value, layer)| {
+            // Don't show the player's token to anyone looking over their shoulder
+            let value = match name.as_str() {
+                "auth_access_token" if !value.is_empty() => String::from("(hidden)"),
+                _ => value,
+            };
+            format!("{0} = {1} ({2})", name, value, layer)
+        }).collect();
+    }
+}
+
+// Finds the "}" that closes a "${", given the text after it
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '$' && chars.peek().is_some_and(|(_, next)| *next == '{') {
+            chars.next();
+            depth += 1;
+        }
+        else if c == '}' {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
             }
-        }).into_owned();
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(variables: &[(&str, &str)]) -> Environment {
+        let mut env = Environment::new();
+        for (name, value) in variables.iter() {
+            env.set(name, value);
+        }
+        return env;
+    }
+
+    #[test]
+    fn resolve_uses_defaults_for_undefined_and_empty_variables() {
+        let env = env(&[("name", "Steve"), ("empty", ""), ("fallback", "Alex")]);
+        assert_eq!(env.resolve_strict("${name:-nobody}"), Ok(String::from("Steve")));
+        assert_eq!(env.resolve_strict("${empty:-nobody}"), Ok(String::from("nobody")));
+        assert_eq!(env.resolve_strict("${missing:-${fallback}}"), Ok(String::from("Alex")));
+        assert_eq!(env.resolve_strict("${missing:-}"), Ok(String::new()));
+        assert_eq!(env.resolve_strict("${empty}"), Ok(String::new()));
+        let errors = env.resolve_strict("${missing} ${other}").unwrap_err();
+        assert_eq!(errors.missing, vec![String::from("missing"), String::from("other")]);
+    }
+
+    #[test]
+    fn resolve_follows_nested_variables_and_reports_cycles() {
+        let env = env(&[("dir", "${root}/game"), ("root", "/home"), ("a", "${b}"), ("b", "x${a}")]);
+        assert_eq!(env.resolve_strict("--gameDir ${dir}"), Ok(String::from("--gameDir /home/game")));
+        let errors = env.resolve_strict("${a}").unwrap_err();
+        assert_eq!(errors.cycles, vec![String::from("a -> b -> a")]);
+        assert!(errors.missing.is_empty());
+        assert_eq!(env.resolve("${a}!"), "x!");
+    }
+
+    #[test]
+    fn resolve_handles_escapes_and_stray_dollars() {
+        let env = env(&[("name", "Steve")]);
+        assert_eq!(env.resolve_strict("$${name} is ${name}"), Ok(String::from("${name} is Steve")));
+        assert_eq!(env.resolve_strict("costs $5, $name"), Ok(String::from("costs $5, $name")));
+        assert_eq!(env.resolve_strict("unterminated ${name"), Ok(String::from("unterminated ${name")));
+    }
+
+    #[test]
+    fn resolve_leaves_literal_values_alone() {
+        let mut env = env(&[("root", "/home")]);
+        env.set_literal("game_directory", "/games/${root}");
+        assert_eq!(env.resolve_strict("${game_directory}"), Ok(String::from("/games/${root}")));
+
+        let mut layered = LayeredEnvironment::new();
+        layered.layer_mut(EnvLayer::Global).set("args", "--dir ${game_directory}");
+        layered.set("game_directory", "/games/${oops}");
+        assert_eq!(layered.resolve_strict("${args}"), Ok(String::from("--dir /games/${oops}")));
+    }
+
+    #[test]
+    fn matching_brace_skips_nested_variables() {
+        assert_eq!(matching_brace("name}"), Some(4));
+        assert_eq!(matching_brace("a:-${b:-${c}}} rest}"), Some(13));
+        assert_eq!(matching_brace("a:-{b}"), Some(5));
+        assert_eq!(matching_brace("a:-${b}"), None);
+        assert_eq!(matching_brace(""), None);
     }
 }
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{file_sha1, write_atomic};
+
+// Identifies a version of a file without reading it
+// If any of these change, the file has to be hashed again
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    size: u64,
+    // Modification time in nanoseconds since the Unix epoch
+    modified: u128,
+    // Catches files being replaced by another file with the same size and time, always 0 on Windows
+    inode: u64,
+}
+
+impl FileStamp {
+    fn of(metadata: &Metadata) -> Option<FileStamp> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(FileStamp {
+            size: metadata.len(),
+            modified: modified,
+            inode: inode,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CacheEntry {
+    stamp: FileStamp,
+    sha1: String,
+}
+
+// Remembers the hashes of downloaded files in "{launcher}/hash_cache.json",
+// so that files that haven't changed since they were last checked don't need to be hashed again
+pub struct HashCache {
+    path: String,
+    // Hash every file even if it looks unchanged, still updating the cache
+    deep_verify: bool,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    changed: Mutex<bool>,
+}
+
+impl HashCache {
+    pub fn open(minecraft_path: &str, deep_verify: bool) -> HashCache {
+        let path = format!("{0}/hash_cache.json", minecraft_path);
+        // A missing or broken cache just means hashing everything again
+        let entries = fs::read_to_string(&path).ok()
+            .and_then(|cache_json| serde_json::from_str(&cache_json).ok())
+            .unwrap_or_default();
+        HashCache {
+            path: path,
+            deep_verify: deep_verify,
+            entries: Mutex::new(entries),
+            changed: Mutex::new(false),
+        }
+    }
+
+    // Writes the cache out if anything changed, forgetting files that have been removed since they were hashed
+    // Goes through a temporary file, so that a crash while saving doesn't lose the whole cache
+    pub fn save(&self) {
+        let mut changed = self.changed.lock().unwrap();
+        let mut entries = self.entries.lock().unwrap();
+        let entry_count = entries.len();
+        entries.retain(|path, _| Path::new(path).exists());
+        if !*changed && entries.len() == entry_count {
+            return;
+        }
+        let cache_json = serde_json::to_string(&*entries).unwrap();
+        if let Err(e) = write_atomic(Path::new(&self.path), cache_json.as_bytes()) {
+            println!("Failed to save the hash cache: {0}", e);
+        }
+        *changed = false;
+    }
+
+    // Checks that a file exists with the right size and SHA-1
+    pub fn check_file(&self, file_path: &Path, sha1: &str, size: u64) -> bool {
+        let metadata = match fs::metadata(file_path) {
+            Ok(metadata) => metadata,
+            Err(_) => return false,
+        };
+        if metadata.len() != size {
+            return false;
+        }
+
+        let key = file_path.to_string_lossy().into_owned();
+        let stamp = FileStamp::of(&metadata);
+        if !self.deep_verify {
+            if let Some(entry) = self.entries.lock().unwrap().get(&key) {
+                if stamp.as_ref() == Some(&entry.stamp) {
+                    return entry.sha1 == sha1;
+                }
+            }
+        }
+
+        let file_hash = match file_sha1(file_path) {
+            Some(file_hash) => file_hash,
+            None => return false,
+        };
+        if let Some(stamp) = stamp {
+            self.entries.lock().unwrap().insert(key, CacheEntry {
+                stamp: stamp,
+                sha1: file_hash.clone(),
+            });
+            *self.changed.lock().unwrap() = true;
+        }
+        return file_hash == sha1;
+    }
+}
@@ -0,0 +1,51 @@
+use std::process::{ExitStatus, Stdio};
+use async_std::process::Command;
+use async_std::io::BufReader;
+use async_std::io::prelude::BufReadExt;
+use futures::stream::{self, StreamExt};
+
+use crate::env::Environment;
+use crate::log::{LogRecord, LogSource, SessionLog};
+use crate::util::get_os;
+
+// Runs a hook command through the shell, with the launcher's variables exported as MINELAUNCH_{NAME}
+// The hook's output goes to the session log
+pub async fn run_hook(hook_name: &str, command: &str, directory: &str, env: &Environment, log: &SessionLog) -> Result<ExitStatus, String> {
+    log.push(LogRecord::launcher(&format!("Running {0} hook: {1}", hook_name, command)));
+    let mut hook_process;
+    if get_os() == "windows" {
+        hook_process = Command::new("cmd");
+        hook_process.args(["/C", command]);
+    }
+    else {
+        hook_process = Command::new("sh");
+        hook_process.args(["-c", command]);
+    }
+    hook_process.current_dir(directory);
+    for (name, value) in env.iter() {
+        // Hooks don't need to be able to log in as the player
+        if name == "auth_access_token" || name == "auth_session" {
+            continue;
+        }
+        hook_process.env(format!("MINELAUNCH_{0}", name.to_uppercase()), env.resolve(value));
+    }
+    hook_process.stdout(Stdio::piped());
+    hook_process.stderr(Stdio::piped());
+    let mut child = match hook_process.spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(format!("Failed to run {0} hook: {1}", hook_name, e)),
+    };
+
+    let stdout_lines = BufReader::new(child.stdout.take().unwrap()).lines().map(|l| (LogSource::Stdout, l));
+    let stderr_lines = BufReader::new(child.stderr.take().unwrap()).lines().map(|l| (LogSource::Stderr, l));
+    let mut output_lines = stream::select(stdout_lines, stderr_lines);
+    while let Some((source, line)) = output_lines.next().await {
+        if let Ok(line) = line {
+            log.push(LogRecord::hook(hook_name, source, &line));
+        }
+    }
+
+    let status = child.status().await.map_err(|e| format!("Failed to wait for the {0} hook: {1}", hook_name, e))?;
+    log.push(LogRecord::launcher(&format!("The {0} hook exited with {1}", hook_name, status)));
+    return Ok(status);
+}
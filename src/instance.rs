@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+use crate::env::Environment;
+use crate::util::get_total_memory;
+
+// Settings for an instance, stored in "{instance}/instance.json"
+// Every field has a default so that older instance files still load
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct InstanceSettings {
+    // Log4j configuration file to use instead of the one from the version spec
+    pub logging_config: Option<String>,
+    // Initial and maximum heap size in megabytes
+    pub min_memory: Option<u32>,
+    pub max_memory: Option<u32>,
+    // Extra arguments added after the ones from the version spec
+    pub jvm_args: Vec<String>,
+    pub game_args: Vec<String>,
+    // Command that Java gets run through, ie ["gamemoderun"] or ["nice", "-n", "5"]
+    pub wrapper_command: Vec<String>,
+    // Environment variables to set for the game, values can use launcher variables like ${game_directory}
+    pub env_vars: BTreeMap<String, String>,
+    // Environment variables to remove from what the game inherits from the launcher
+    pub unset_env_vars: Vec<String>,
+    // Shell commands run in the game directory before launching and after the game exits
+    // A failing pre-launch hook stops the launch
+    pub pre_launch_hook: Option<String>,
+    pub post_exit_hook: Option<String>,
+    // Version this instance was last launched with, so that its files are kept when pruning
+    pub last_version: Option<String>,
+}
+
+// An instance is a separate game directory with its own saves, options and settings
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub path: String,
+    pub settings: InstanceSettings,
+}
+
+impl Instance {
+    // An instance with the default settings, whether or not it has been saved
+    pub fn new(minecraft_path: &str, name: &str) -> Instance {
+        Instance {
+            name: name.to_string(),
+            path: format!("{0}/instances/{1}", minecraft_path, name),
+            settings: InstanceSettings::default(),
+        }
+    }
+
+    pub fn load(minecraft_path: &str, name: &str) -> Result<Instance, String> {
+        let mut instance = Instance::new(minecraft_path, name);
+        instance.settings = Instance::load_settings(&instance.path)?;
+        return Ok(instance);
+    }
+
+    // Reads the settings saved in an instance's directory
+    // Falls back to the default settings if the instance hasn't been saved yet
+    pub fn load_settings(path: &str) -> Result<InstanceSettings, String> {
+        let settings_path = format!("{0}/instance.json", path);
+        match File::open(&settings_path) {
+            Ok(mut settings_file) => {
+                let mut settings_json = String::new();
+                settings_file.read_to_string(&mut settings_json).map_err(|e| format!("Failed to read {0}: {1}", settings_path, e))?;
+                serde_json::from_str(&settings_json).map_err(|e| format!("Failed to parse {0}: {1}", settings_path, e))
+            },
+            Err(_) => Ok(InstanceSettings::default()),
+        }
+    }
+
+    pub fn save(&self) {
+        fs::create_dir_all(&self.path).unwrap();
+        let settings_json = serde_json::to_string_pretty(&self.settings).unwrap();
+        let mut settings_file = File::create(format!("{0}/instance.json", self.path)).unwrap();
+        settings_file.write_all(settings_json.as_bytes()).unwrap();
+    }
+
+    // Launcher variables for this instance, kept in TOML alongside the instance's settings
+    pub fn environment_path(&self) -> String {
+        return format!("{0}/environment.toml", self.path);
+    }
+
+    pub fn load_environment(&self) -> Result<Environment, String> {
+        return Environment::load(&self.environment_path());
+    }
+
+    // Instance names become folder names, so they can't contain path separators
+    pub fn is_valid_name(name: &str) -> bool {
+        return !name.trim().is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':']);
+    }
+
+    // Names of all instances, always including the default instance
+    pub fn list(minecraft_path: &str) -> Vec<String> {
+        let mut names = vec![String::from("default")];
+        let instances_path = format!("{0}/instances/", minecraft_path);
+        if Path::new(&instances_path).exists() {
+            for entry in fs::read_dir(&instances_path).unwrap() {
+                let entry = entry.unwrap();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().is_dir() && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names[1..].sort();
+        return names;
+    }
+}
+
+// What the game used to keep in the launcher directory, before it got its own directory per instance
+const LEGACY_GAME_FILES: [&str; 21] = [
+    "saves", "options.txt", "optionsof.txt", "optionsshaders.txt", "servers.dat", "servers.dat_old", "usercache.json",
+    "resourcepacks", "texturepacks", "shaderpacks", "screenshots", "crash-reports", "logs", "stats", "mods", "config",
+    "hotbar.nbt", "realms_persistence.json", "command_history.txt", "lastlogin", "schematics",
+];
+
+// Moves the game files left in the launcher directory by older launcher versions into the default instance
+// Only done while the default instance doesn't exist yet, so nothing in it can get overwritten
+pub fn migrate_legacy_game_directory(minecraft_path: &str) {
+    let default_path = format!("{0}/instances/default", minecraft_path);
+    if Path::new(&default_path).exists() {
+        return;
+    }
+    let legacy_files: Vec<&str> = LEGACY_GAME_FILES.iter().copied().filter(|f| Path::new(&format!("{0}/{1}", minecraft_path, f)).exists()).collect();
+    if legacy_files.is_empty() {
+        return;
+    }
+
+    println!("Moving the game files in {0} into the default instance", minecraft_path);
+    fs::create_dir_all(&default_path).unwrap();
+    for file in legacy_files {
+        let from = format!("{0}/{1}", minecraft_path, file);
+        let to = format!("{0}/{1}", default_path, file);
+        if let Err(e) = fs::rename(&from, &to) {
+            println!("Failed to move {0} to {1}, it's still in the launcher directory: {2}", from, to, e);
+        }
+    }
+}
+
+// Suggests a maximum heap size in megabytes based on the system's memory
+// Leaves half of the memory for the OS and the JVM's own overhead, within sensible bounds
+pub fn suggested_max_memory() -> Option<u32> {
+    let total_memory = get_total_memory()?;
+    let suggestion = (total_memory / 2).clamp(1024, 8192);
+    return Some((suggestion / 512 * 512) as u32);
+}
@@ -0,0 +1,49 @@
+// Extra JVM features for debugging and profiling the game, chosen when launching
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchModes {
+    // Port for a JDWP agent that debuggers can attach to, or None to not start one
+    pub jdwp_port: Option<u16>,
+    // Wait for a debugger to attach before the game starts
+    pub jdwp_suspend: bool,
+    // Record with Java Flight Recorder, written out when the game exits
+    pub flight_recorder: bool,
+    pub gc_logging: bool,
+}
+
+// The port debuggers usually expect
+pub const DEFAULT_JDWP_PORT: u16 = 5005;
+
+impl LaunchModes {
+    // JVM arguments for the enabled modes, along with the files they'll write
+    // Output goes to "{game_directory}/debug/", named after the session so launches don't overwrite each other
+    // The game runs in its game directory, so the options use paths relative to it
+    // Option strings are split on ',' and ':', which instance paths can contain, ie "C:\Users\..." or an instance called "a,b"
+    pub fn jvm_args(&self, game_directory: &str, java_version: u8, session_time: u64) -> (Vec<String>, Vec<String>) {
+        let mut args = Vec::new();
+        let mut output_files = Vec::new();
+
+        if let Some(port) = self.jdwp_port {
+            let suspend = if self.jdwp_suspend { "y" } else { "n" };
+            args.push(format!("-agentlib:jdwp=transport=dt_socket,server=y,suspend={0},address={1}", suspend, port));
+        }
+        if self.flight_recorder {
+            let recording_path = format!("debug/recording-{0}.jfr", session_time);
+            args.push(format!("-XX:StartFlightRecording=dumponexit=true,filename={0}", recording_path));
+            output_files.push(format!("{0}/{1}", game_directory, recording_path));
+        }
+        if self.gc_logging {
+            let gc_log_path = format!("debug/gc-{0}.log", session_time);
+            // Unified logging replaced the old GC logging flags in Java 9
+            if java_version >= 9 {
+                args.push(format!("-Xlog:gc*:file={0}:time,uptime,level,tags", gc_log_path));
+            }
+            else {
+                args.push(format!("-Xloggc:{0}", gc_log_path));
+                args.push(String::from("-XX:+PrintGCDetails"));
+                args.push(String::from("-XX:+PrintGCDateStamps"));
+            }
+            output_files.push(format!("{0}/{1}", game_directory, gc_log_path));
+        }
+        return (args, output_files);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// How files that duplicate an asset object get put in place
+// Hardlinks share the object itself, so the game changing one would change the object too,
+// which the next verification catches and downloads again
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetLinkMode {
+    // Hardlink, falling back to a reflink and then a copy
+    #[default]
+    Auto,
+    Hardlink,
+    // Copy-on-write clone, on filesystems that support it like Btrfs, XFS and APFS
+    Reflink,
+    Copy,
+}
+
+impl AssetLinkMode {
+    pub const ALL: [AssetLinkMode; 4] = [AssetLinkMode::Auto, AssetLinkMode::Hardlink, AssetLinkMode::Reflink, AssetLinkMode::Copy];
+
+    // Whether copies left by older launches should be turned into hardlinks
+    // Reflinks can't be told apart from copies, so those are left alone
+    pub fn converts_copies(&self) -> bool {
+        return matches!(self, AssetLinkMode::Auto | AssetLinkMode::Hardlink);
+    }
+}
+
+impl fmt::Display for AssetLinkMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AssetLinkMode::Auto => "Auto",
+            AssetLinkMode::Hardlink => "Hardlink",
+            AssetLinkMode::Reflink => "Reflink",
+            AssetLinkMode::Copy => "Copy",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// How a file ended up being put in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Hardlinked,
+    Reflinked,
+    Copied,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Placement::Hardlinked => "hardlinked",
+            Placement::Reflinked => "reflinked",
+            Placement::Copied => "copied",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+// Puts a copy of source at target, replacing whatever is there
+// Links can fail across filesystems or on filesystems without support, so each mode falls back to copying
+pub fn place_file(source: &Path, target: &Path, mode: AssetLinkMode) -> io::Result<Placement> {
+    // Go through a temporary file so target is only ever replaced by a complete file
+    let temp_path = temp_path(target);
+    let _ = fs::remove_file(&temp_path);
+
+    let mut placement = None;
+    if matches!(mode, AssetLinkMode::Auto | AssetLinkMode::Hardlink) && fs::hard_link(source, &temp_path).is_ok() {
+        placement = Some(Placement::Hardlinked);
+    }
+    if placement.is_none() && matches!(mode, AssetLinkMode::Auto | AssetLinkMode::Reflink) && reflink_copy::reflink(source, &temp_path).is_ok() {
+        placement = Some(Placement::Reflinked);
+    }
+    if placement.is_none() {
+        fs::copy(source, &temp_path)?;
+        placement = Some(Placement::Copied);
+    }
+
+    if let Err(e) = fs::rename(&temp_path, target) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    return Ok(placement.unwrap());
+}
+
+// Replaces target with a hardlink to source, for turning existing copies into links
+// Returns false and leaves target alone if it's already linked or it can't be linked, ie it's on another filesystem
+pub fn convert_to_hardlink(source: &Path, target: &Path) -> bool {
+    if !can_hardlink(source, target) {
+        return false;
+    }
+    let temp_path = temp_path(target);
+    let _ = fs::remove_file(&temp_path);
+    if fs::hard_link(source, &temp_path).is_err() {
+        return false;
+    }
+    if fs::rename(&temp_path, target).is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return false;
+    }
+    return true;
+}
+
+// Whether target is a separate file on the same filesystem as source
+#[cfg(unix)]
+fn can_hardlink(source: &Path, target: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(source), fs::metadata(target)) {
+        (Ok(source), Ok(target)) => source.dev() == target.dev() && source.ino() != target.ino(),
+        _ => false,
+    }
+}
+
+// Compares file IDs instead, which means opening both files
+// Files on different volumes can't be linked, which hard_link finds out on its own
+#[cfg(not(unix))]
+fn can_hardlink(source: &Path, target: &Path) -> bool {
+    return same_file::is_same_file(source, target).is_ok_and(|same| !same);
+}
+
+fn temp_path(target: &Path) -> PathBuf {
+    let mut file_name = target.file_name().unwrap().to_os_string();
+    file_name.push(".minelaunch-tmp");
+    return target.with_file_name(file_name);
+}
@@ -0,0 +1,288 @@
+use std::sync::Mutex;
+use std::fs::File;
+use std::io::Write;
+use regex::Regex;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref EVENT_REGEX: Regex = Regex::new(r#"(?s)<log4j:Event\s([^>]*)>(.*)</log4j:Event>"#).unwrap();
+    static ref ATTRIBUTE_REGEX: Regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+    static ref MESSAGE_REGEX: Regex = Regex::new(r"(?s)<log4j:Message>(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?</log4j:Message>").unwrap();
+    static ref THROWABLE_REGEX: Regex = Regex::new(r"(?s)<log4j:Throwable>(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?</log4j:Throwable>").unwrap();
+    // Matches the plain text layout used by Minecraft, ie "[12:34:56] [Render thread/INFO]: message"
+    static ref PLAIN_REGEX: Regex = Regex::new(r"^\[([0-9:]+)\] \[([^\]]*)/([A-Z]+)\]:? ?(.*)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    Unknown,
+}
+
+impl LogLevel {
+    fn parse(level: &str) -> LogLevel {
+        match level {
+            "TRACE" => LogLevel::Trace,
+            "DEBUG" => LogLevel::Debug,
+            "INFO" => LogLevel::Info,
+            "WARN" => LogLevel::Warn,
+            "ERROR" => LogLevel::Error,
+            "FATAL" => LogLevel::Fatal,
+            _ => LogLevel::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{0}", match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+            LogLevel::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    Stdout,
+    Stderr,
+    Launcher,
+    Hook,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub time: Option<String>,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    pub message: String,
+    pub throwable: Option<String>,
+}
+
+impl LogRecord {
+    pub fn plain(source: LogSource, line: &str) -> LogRecord {
+        // Try to pick the level out of Minecraft's usual text layout
+        if let Some(captures) = PLAIN_REGEX.captures(line) {
+            return LogRecord {
+                level: LogLevel::parse(&captures[3]),
+                source: source,
+                time: Some(captures[1].to_string()),
+                thread: Some(captures[2].to_string()),
+                logger: None,
+                message: captures[4].to_string(),
+                throwable: None,
+            };
+        }
+
+        // Anything unstructured on stderr is most likely a stack trace or a JVM error
+        let level = match source {
+            LogSource::Stderr => LogLevel::Error,
+            _ => LogLevel::Unknown,
+        };
+        LogRecord {
+            level: level,
+            source: source,
+            time: None,
+            thread: None,
+            logger: None,
+            message: line.to_string(),
+            throwable: None,
+        }
+    }
+
+    // Messages from the launcher itself, shown alongside the game's output
+    pub fn launcher(message: &str) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Info,
+            source: LogSource::Launcher,
+            time: None,
+            thread: Some(String::from("Launcher")),
+            logger: None,
+            message: message.to_string(),
+            throwable: None,
+        }
+    }
+
+    // Output from a pre-launch or post-exit hook
+    pub fn hook(hook_name: &str, source: LogSource, line: &str) -> LogRecord {
+        let level = match source {
+            LogSource::Stderr => LogLevel::Warn,
+            _ => LogLevel::Info,
+        };
+        LogRecord {
+            level: level,
+            source: LogSource::Hook,
+            time: None,
+            thread: Some(format!("{0} hook", hook_name)),
+            logger: None,
+            message: line.to_string(),
+            throwable: None,
+        }
+    }
+
+}
+
+impl std::fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(time) = &self.time {
+            write!(f, "[{0}] ", time)?;
+        }
+        if let Some(thread) = &self.thread {
+            write!(f, "[{0}/{1}] ", thread, self.level)?;
+        }
+        if let Some(logger) = &self.logger {
+            write!(f, "({0}) ", logger)?;
+        }
+        write!(f, "{0}", self.message)?;
+        if let Some(throwable) = &self.throwable {
+            write!(f, "\n{0}", throwable.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// Turns the lines of a game's stdout into log records
+// Versions with a logging config print log4j XML events, which span multiple lines and get buffered until complete
+pub struct LogParser {
+    event_buffer: Option<String>,
+}
+
+impl LogParser {
+    pub fn new() -> LogParser {
+        LogParser {
+            event_buffer: None,
+        }
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Option<LogRecord> {
+        match self.event_buffer.as_mut() {
+            Some(event) => {
+                event.push('\n');
+                event.push_str(line);
+            },
+            // The XML layout puts blank lines between events
+            None if line.trim().is_empty() => return None,
+            None if line.trim_start().starts_with("<log4j:Event") => {
+                self.event_buffer = Some(line.to_string());
+            },
+            None => return Some(LogRecord::plain(LogSource::Stdout, line)),
+        }
+
+        if line.contains("</log4j:Event>") {
+            let event = self.event_buffer.take().unwrap();
+            return Some(parse_event(&event).unwrap_or_else(|| LogRecord::plain(LogSource::Stdout, &event)));
+        }
+        return None;
+    }
+}
+
+fn parse_event(event: &str) -> Option<LogRecord> {
+    let captures = EVENT_REGEX.captures(event)?;
+    let mut record = LogRecord {
+        level: LogLevel::Unknown,
+        source: LogSource::Stdout,
+        time: None,
+        thread: None,
+        logger: None,
+        message: String::new(),
+        throwable: None,
+    };
+
+    for attribute in ATTRIBUTE_REGEX.captures_iter(&captures[1]) {
+        let value = unescape_xml(&attribute[2]);
+        match &attribute[1] {
+            "level" => record.level = LogLevel::parse(&value),
+            "thread" => record.thread = Some(value),
+            "logger" => record.logger = Some(value),
+            "timestamp" => record.time = value.parse::<u64>().ok().map(format_timestamp),
+            _ => (),
+        }
+    }
+
+    let body = &captures[2];
+    if let Some(message) = MESSAGE_REGEX.captures(body) {
+        record.message = message[1].to_string();
+    }
+    if let Some(throwable) = THROWABLE_REGEX.captures(body) {
+        record.throwable = Some(throwable[1].to_string());
+    }
+    return Some(record);
+}
+
+fn unescape_xml(text: &str) -> String {
+    return text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+}
+
+// Formats a log4j millisecond timestamp as HH:MM:SS in UTC
+fn format_timestamp(timestamp: u64) -> String {
+    let seconds = timestamp / 1000 % 86400;
+    return format!("{0:02}:{1:02}:{2:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+}
+
+// Log records for one run of the game, shared between the launch task and the GUI
+// Records also get written to the session's log file once it's been created
+pub struct SessionLog {
+    // Version of the game that's running, if any
+    pub version: Option<String>,
+    records: Mutex<Vec<LogRecord>>,
+    file: Mutex<Option<(String, File)>>,
+}
+
+impl SessionLog {
+    pub fn new(version: Option<String>) -> SessionLog {
+        SessionLog {
+            version: version,
+            records: Mutex::new(Vec::new()),
+            file: Mutex::new(None),
+        }
+    }
+
+    // If the log file can't be written to, ie because the disk is full, it's reported once and the rest is only kept in memory
+    pub fn push(&self, record: LogRecord) {
+        let mut file = self.file.lock().unwrap();
+        if let Some((path, log_file)) = file.as_mut() {
+            if let Err(e) = writeln!(log_file, "{0}", record) {
+                println!("Failed to write to {0}, no longer saving the log: {1}", path, e);
+                *file = None;
+            }
+        }
+        // Released before taking the records, which create_file takes first
+        drop(file);
+        self.records.lock().unwrap().push(record);
+    }
+
+    // Records from the given index on, for picking up only what's new
+    pub fn records_since(&self, start: usize) -> Vec<LogRecord> {
+        return self.records.lock().unwrap().iter().skip(start).cloned().collect();
+    }
+
+    pub fn file_path(&self) -> Option<String> {
+        return self.file.lock().unwrap().as_ref().map(|(path, _)| path.clone());
+    }
+
+    pub fn create_file(&self, path: &str) -> Result<(), String> {
+        let mut file = File::create(path).map_err(|e| format!("Failed to create {0}: {1}", path, e))?;
+        // Write out anything logged before the file existed
+        for record in self.records.lock().unwrap().iter() {
+            writeln!(file, "{0}", record).map_err(|e| format!("Failed to write to {0}: {1}", path, e))?;
+        }
+        *self.file.lock().unwrap() = Some((path.to_string(), file));
+        return Ok(());
+    }
+}
@@ -1,24 +1,115 @@
 mod minecraft;
+mod allowlist;
+#[cfg(test)]
+mod bench;
+mod cli;
+mod config;
+mod crash;
 mod env;
+mod hash_cache;
+mod hooks;
+mod instance;
+mod launch_mode;
+mod link;
+mod log;
+mod mappings;
+mod mirror;
+mod net;
+mod pool;
+mod process;
+mod proxy;
+mod prune;
+mod repair;
+mod spec;
 mod util;
 
-use std::process::ExitStatus;
-use iced::{Alignment, Application, Button, Column, Command, Container, Element, Length, PickList, Row, Settings, Space, Subscription, Text, TextInput};
-use iced::{button, executor, pick_list, text_input, window};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use iced::{Alignment, Application, Button, Checkbox, Color, Column, Command, Container, Element, Length, PickList, Row, Scrollable, Settings, Space, Subscription, Text, TextInput};
+use iced::{button, clipboard, executor, pick_list, scrollable, text_input, time, window};
 use async_std::task;
 
-use minecraft::{MinecraftVersionList, MinecraftVersion, launch_minecraft_version, download_minecraft_version};
-use env::Environment;
+use minecraft::{GameExit, DryLaunch, get_version_list, custom_versions, installed_versions, launch_minecraft_version, dry_launch_minecraft_version, download_minecraft_version, reinstall_minecraft_version, get_client_mappings, deobfuscate_file};
+use config::LauncherConfig;
+use crash::CrashKind;
+use env::{EnvLayer, Environment, LayeredEnvironment};
+use instance::{Instance, suggested_max_memory};
+use launch_mode::{LaunchModes, DEFAULT_JDWP_PORT};
+use link::AssetLinkMode;
+use log::{LogLevel, LogRecord, LogSource, SessionLog};
+use mappings::Mappings;
+use process::{ProcessRegistry, format_uptime};
+use prune::{PruneReport, uninstall_version, version_disk_usage};
+use repair::{VerifyReport, repair_version, verify_version};
+use spec::{MinecraftVersionList, MinecraftVersion};
+use util::{format_size, open_path, split_args, join_args};
+
+// Where versions, libraries, assets, runtimes and instances are stored
+const LAUNCHER_PATH: &str = ".";
+
+// LAUNCHER_PATH as an absolute path, since games run in their instance's directory instead
+fn launcher_path() -> String {
+    return std::path::absolute(LAUNCHER_PATH).unwrap().to_str().unwrap().to_string();
+}
+
+// Variables every launch starts with, before the version and instance add their own
+fn default_environment() -> Environment {
+    let mut env = Environment::new();
+    env.set("launcher_name", "Minelaunch");
+    env.set("launcher_version", env!("CARGO_PKG_VERSION"));
+    env.set("auth_player_name", "");
+    env.set("auth_uuid", ""); // TODO: Allow logging in
+    env.set("auth_access_token", "");
+    env.set("auth_xuid", ""); // Only used for Xbox accounts
+    env.set("clientid", "");
+    env.set("user_type", "offline"); // mojang for Mojang, msa for Microsoft
+    // Older versions also want these
+    env.set("auth_session", "${auth_access_token}");
+    env.set("user_properties", "{}");
+    return env;
+}
+
+fn account_path(minecraft_path: &str) -> String {
+    return format!("{0}/account.toml", minecraft_path);
+}
+
+// The layers every launch starts with, the instance and launch layers get added when launching
+fn launcher_environment(minecraft_path: &str) -> LayeredEnvironment {
+    let mut env = LayeredEnvironment::new();
+    env.set_layer(EnvLayer::Defaults, default_environment());
+    let layer_paths = [
+        (EnvLayer::Global, format!("{0}/environment.toml", minecraft_path)),
+        (EnvLayer::Account, account_path(minecraft_path)),
+    ];
+    for (layer, path) in layer_paths {
+        match Environment::load(&path) {
+            Ok(layer_env) => env.set_layer(layer, layer_env),
+            Err(e) => println!("{0}, ignoring it", e),
+        }
+    }
+    return env;
+}
 
 fn main() -> iced::Result {
+    // Run a command instead of the GUI if one was given
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    net::configure(&LauncherConfig::load(&launcher_path()));
+    instance::migrate_legacy_game_directory(&launcher_path());
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     // Launch the GUI
     let settings = Settings {
         window: window::Settings {
-            size: (320, 440),
+            size: (480, 500),
             min_size: Some((320, 230)),
             icon: Some(window::Icon::from_rgba(include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/icon.raw")).to_vec(), 128, 128).unwrap()),
             ..window::Settings::default()
         },
+        // Closing is handled by the GUI so that running games can be dealt with first
+        exit_on_close_request: false,
         ..Settings::default()
     };
     GUI::run(settings)
@@ -32,13 +123,28 @@ enum VersionSelection {
 }
 
 impl VersionSelection {
-    fn make_list(version_list: &MinecraftVersionList) -> Vec<VersionSelection> {
-        let mut list = vec![VersionSelection::Latest(version_list.latest.release.clone()),
-                            VersionSelection::LatestSnapshot(version_list.latest.snapshot.clone())];
-        for v in version_list.versions.iter() {
-            list.push(VersionSelection::Version(v.clone()));
-        }
-        return list;
+    fn version(&self, version_list: &MinecraftVersionList) -> MinecraftVersion {
+        let mut version = version_list.versions.first().unwrap();
+        match self {
+            VersionSelection::Latest(id) => {
+                for v in version_list.versions.iter() {
+                    if v.id == *id {
+                        version = v;
+                        break;
+                    }
+                }
+            },
+            VersionSelection::LatestSnapshot(id) => {
+                for v in version_list.versions.iter() {
+                    if v.id == *id {
+                        version = v;
+                        break;
+                    }
+                }
+            },
+            VersionSelection::Version(v) => { version = v; },
+        };
+        return version.clone();
     }
 }
 
@@ -47,7 +153,7 @@ impl std::fmt::Display for VersionSelection {
         write!(f, "{0}", match self {
             VersionSelection::Latest(id) => format!("Latest Release ({0})", id),
             VersionSelection::LatestSnapshot(id) => format!("Latest Snapshot ({0})", id),
-            VersionSelection::Version(v) => format!("{0} {1}", v.version_type, v.id),
+            VersionSelection::Version(v) => format!("{0} {1} ({2})", v.version_type, v.id, v.release_time.get(..10).unwrap_or_default()),
         })
     }
 }
@@ -55,30 +161,66 @@ impl std::fmt::Display for VersionSelection {
 struct ApplicationState {
     launcher_path: String,
     versions: MinecraftVersionList,
-    env: Environment,
+    env: LayeredEnvironment,
+    config: LauncherConfig,
+    // IDs of the versions with a spec downloaded
+    installed_versions: Vec<String>,
+    // Installed versions that aren't in the version list
+    custom_versions: Vec<MinecraftVersion>,
+    // Output of the most recently launched game
+    session_log: Arc<SessionLog>,
+    processes: Arc<ProcessRegistry>,
 }
 
+impl ApplicationState {
+    // Picks up versions that were just installed or uninstalled
+    fn refresh_installed(&mut self) {
+        self.installed_versions = installed_versions(&self.launcher_path);
+        self.custom_versions = custom_versions(&self.launcher_path, &self.versions);
+    }
+}
+
+#[derive(PartialEq, Eq)]
 enum Tab {
     Launcher,
+    Instance,
     Downloader,
+    Logs,
 }
 
 struct GUI {
     state: ApplicationState,
     tab: Tab,
     launcher_tab: Launcher,
+    instance_tab: InstanceEditor,
     downloader_tab: Downloader,
+    logs_tab: Logs,
+    // Set when closing the window while games are still running
+    exit_prompt: bool,
+    should_exit: bool,
 
     launcher_button_state: button::State,
+    instance_button_state: button::State,
     downloader_button_state: button::State,
+    logs_button_state: button::State,
+    leave_running_button_state: button::State,
+    terminate_button_state: button::State,
+    cancel_exit_button_state: button::State,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     LauncherPressed,
+    InstancePressed,
     DownloaderPressed,
+    LogsPressed,
     LauncherMessage(LauncherMessage),
+    InstanceMessage(InstanceMessage),
     DownloaderMessage(DownloaderMessage),
+    LogsMessage(LogsMessage),
+    CloseRequested,
+    ExitPressed(bool),
+    ExitCancelled,
 }
 
 impl Application for GUI {
@@ -87,35 +229,50 @@ impl Application for GUI {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        let minecraft_path = ".";
-        let mut env = Environment::new();
-        env.set("game_directory", minecraft_path);
-        env.set("launcher_name", "Minelaunch");
-        env.set("launcher_version", env!("CARGO_PKG_VERSION"));
-        env.set("auth_player_name", "");
-        env.set("auth_uuid", ""); // TODO: Allow logging in
-        env.set("auth_access_token", "");
-        env.set("user_type", "offline"); // mojang for Mojang, msa for Microsoft
+        let minecraft_path = launcher_path();
+        let env = launcher_environment(&minecraft_path);
+        let config = LauncherConfig::load(&minecraft_path);
+        let installed = installed_versions(&minecraft_path);
 
         // Get list of Minecraft versions
-        let minecraft_versions_response = task::block_on(reqwest::get("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json")).unwrap();
-        let minecraft_versions_text = task::block_on(minecraft_versions_response.text()).unwrap();
-        let minecraft_versions: MinecraftVersionList = serde_json::from_str(&minecraft_versions_text).unwrap();
+        // Nothing can be launched or downloaded without it
+        let minecraft_versions = match task::block_on(get_version_list(&minecraft_path)) {
+            Ok(versions) => versions,
+            Err(e) => {
+                eprintln!("{0}", e);
+                std::process::exit(1);
+            },
+        };
+        let custom = custom_versions(&minecraft_path, &minecraft_versions);
 
         let state = ApplicationState {
-            launcher_path: minecraft_path.to_string(),
+            launcher_path: minecraft_path,
             versions: minecraft_versions,
             env: env,
+            config: config,
+            custom_versions: custom,
+            installed_versions: installed,
+            session_log: Arc::new(SessionLog::new(None)),
+            processes: Arc::new(ProcessRegistry::new()),
         };
 
         let gui_state = Self {
             tab: Tab::Launcher,
             launcher_tab: Launcher::new(&state),
+            instance_tab: InstanceEditor::new(&state),
             downloader_tab: Downloader::new(&state),
+            logs_tab: Logs::new(),
             state: state,
+            exit_prompt: false,
+            should_exit: false,
 
             launcher_button_state: button::State::default(),
+            instance_button_state: button::State::default(),
             downloader_button_state: button::State::default(),
+            logs_button_state: button::State::default(),
+            leave_running_button_state: button::State::default(),
+            terminate_button_state: button::State::default(),
+            cancel_exit_button_state: button::State::default(),
         };
         return (gui_state, Command::none());
     }
@@ -124,7 +281,33 @@ impl Application for GUI {
         return String::from("Minelaunch");
     }
 
-    fn view(&mut self) -> Element<Message> {
+    fn view(&mut self) -> Element<'_, Message> {
+        if self.exit_prompt {
+            let content = Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new(format!("{0} game(s) still running.", self.state.processes.list().len())))
+                .push(Space::with_height(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.leave_running_button_state, Text::new("Leave Running"))
+                        .on_press(Message::ExitPressed(false))
+                ).push(Space::with_height(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.terminate_button_state, Text::new("Terminate"))
+                        .on_press(Message::ExitPressed(true))
+                ).push(Space::with_height(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.cancel_exit_button_state, Text::new("Cancel"))
+                        .on_press(Message::ExitCancelled)
+                );
+
+            return Container::new(content)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y()
+                .into();
+        }
+
         let mut content = Column::new()
             .align_items(Alignment::Center)
             .push(Space::with_height(Length::Units(10)))
@@ -137,18 +320,32 @@ impl Application for GUI {
                     Button::new(&mut self.launcher_button_state, Text::new("Launcher"))
                         .on_press(Message::LauncherPressed)
                 ).push(Space::with_width(Length::Units(20)))
+                .push(
+                    Button::new(&mut self.instance_button_state, Text::new("Instance"))
+                        .on_press(Message::InstancePressed)
+                ).push(Space::with_width(Length::Units(20)))
                 .push(
                     Button::new(&mut self.downloader_button_state, Text::new("Downloader"))
                         .on_press(Message::DownloaderPressed)
+                ).push(Space::with_width(Length::Units(20)))
+                .push(
+                    Button::new(&mut self.logs_button_state, Text::new("Logs"))
+                        .on_press(Message::LogsPressed)
             )).push(Space::with_height(Length::Units(10)));
 
         match self.tab {
             Tab::Launcher => {
                 content = content.push(self.launcher_tab.view(&self.state));
             }
+            Tab::Instance => {
+                content = content.push(self.instance_tab.view(&self.state));
+            }
             Tab::Downloader => {
                 content = content.push(self.downloader_tab.view(&self.state));
             }
+            Tab::Logs => {
+                content = content.push(self.logs_tab.view(&self.state));
+            }
         }
 
         return Container::new(content)
@@ -164,61 +361,172 @@ impl Application for GUI {
             Message::LauncherPressed => {
                 self.tab = Tab::Launcher;
             },
+            Message::InstancePressed => {
+                self.tab = Tab::Instance;
+            },
             Message::DownloaderPressed => {
                 self.tab = Tab::Downloader;
+                return self.downloader_tab.load_disk_usage(&self.state);
+            },
+            Message::LogsPressed => {
+                self.tab = Tab::Logs;
+                return self.logs_tab.update(&mut self.state, LogsMessage::Tick);
             },
             Message::LauncherMessage(launcher_msg) => {
                 return self.launcher_tab.update(&mut self.state, launcher_msg);
             },
+            Message::InstanceMessage(instance_msg) => {
+                return self.instance_tab.update(&mut self.state, instance_msg);
+            },
             Message::DownloaderMessage(downloader_msg) => {
                 return self.downloader_tab.update(&mut self.state, downloader_msg);
             },
+            Message::LogsMessage(logs_msg) => {
+                return self.logs_tab.update(&mut self.state, logs_msg);
+            },
+            Message::CloseRequested => {
+                if self.state.processes.list().is_empty() {
+                    self.should_exit = true;
+                }
+                else {
+                    self.exit_prompt = true;
+                }
+            },
+            Message::ExitPressed(terminate) => {
+                if terminate {
+                    self.state.processes.kill_all();
+                }
+                self.should_exit = true;
+            },
+            Message::ExitCancelled => {
+                self.exit_prompt = false;
+            },
         }
         return Command::none();
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        return Subscription::none();
+        let close_requests = iced_native::subscription::events_with(|event, _status| {
+            match event {
+                iced_native::Event::Window(iced_native::window::Event::CloseRequested) => Some(Message::CloseRequested),
+                _ => None,
+            }
+        });
+
+        let tick = match self.tab {
+            // Keep the uptime of running games up to date
+            Tab::Launcher => time::every(Duration::from_secs(1)).map(|_| Message::LauncherMessage(LauncherMessage::Tick)),
+            // Poll the session log for new output while the log viewer is open
+            Tab::Logs => time::every(Duration::from_millis(250)).map(|_| Message::LogsMessage(LogsMessage::Tick)),
+            _ => Subscription::none(),
+        };
+        return Subscription::batch(vec![close_requests, tick]);
+    }
+
+    fn should_exit(&self) -> bool {
+        return self.should_exit;
     }
 }
 
 #[derive(Debug, Clone)]
 enum LauncherMessage {
     LaunchPressed,
-    VersionSelected(VersionSelection),
+    DryLaunchPressed,
+    DryLaunchFinished(Result<DryLaunch, String>),
+    CopyLaunchCommandPressed,
+    JdwpToggled(bool),
+    JdwpPortChanged(String),
+    JdwpSuspendToggled(bool),
+    FlightRecorderToggled(bool),
+    GcLoggingToggled(bool),
+    VersionList(VersionListMessage),
+    InstanceSelected(String),
     UsernameChanged(String),
-    MinecraftExited(ExitStatus),
+    MinecraftExited(Result<GameExit, String>),
+    KillPressed(u64),
+    OpenCrashReportPressed,
+    OpenLogPressed,
+    DeobfuscatePressed,
+    DeobfuscateFinished(Result<String, String>),
+    Tick,
 }
 
 struct Launcher {
     selected_version: VersionSelection,
-    last_exit_status: Option<ExitStatus>,
+    selected_instance: String,
+    last_exit: Option<GameExit>,
+    launch_error: Option<String>,
+    dry_launch: Option<DryLaunch>,
     username: String,
+    // Set after trying to launch an instance that's already running
+    duplicate_warning: bool,
+    // Launch modes, with the JDWP port kept as text while it's being edited
+    jdwp: bool,
+    jdwp_port: String,
+    jdwp_suspend: bool,
+    flight_recorder: bool,
+    gc_logging: bool,
+    version_list: VersionList,
 
     launch_button_state: button::State,
-    version_dropdown_state: pick_list::State<VersionSelection>,
+    dry_launch_button_state: button::State,
+    copy_launch_command_button_state: button::State,
+    instance_dropdown_state: pick_list::State<String>,
     username_input_state: text_input::State,
+    jdwp_port_input_state: text_input::State,
+    kill_button_states: Vec<button::State>,
+    // Set if deobfuscating the last crash report failed
+    deobfuscate_error: Option<String>,
+    open_crash_report_button_state: button::State,
+    open_log_button_state: button::State,
+    deobfuscate_button_state: button::State,
 }
 
 impl Launcher {
     fn new(state: &ApplicationState) -> Self {
         Launcher {
             selected_version: VersionSelection::Latest(state.versions.latest.release.clone()),
-            last_exit_status: None,
-            username: String::from(""),
+            selected_instance: String::from("default"),
+            last_exit: None,
+            launch_error: None,
+            dry_launch: None,
+            username: state.env.layer(EnvLayer::Account).and_then(|a| a.get("auth_player_name")).cloned().unwrap_or_default(),
+            duplicate_warning: false,
+            jdwp: false,
+            jdwp_port: DEFAULT_JDWP_PORT.to_string(),
+            jdwp_suspend: false,
+            flight_recorder: false,
+            gc_logging: false,
+            version_list: VersionList::new(),
 
             launch_button_state: button::State::default(),
-            version_dropdown_state: pick_list::State::default(),
+            dry_launch_button_state: button::State::default(),
+            copy_launch_command_button_state: button::State::default(),
+            instance_dropdown_state: pick_list::State::default(),
             username_input_state: text_input::State::default(),
+            jdwp_port_input_state: text_input::State::default(),
+            kill_button_states: Vec::new(),
+            deobfuscate_error: None,
+            open_crash_report_button_state: button::State::default(),
+            open_log_button_state: button::State::default(),
+            deobfuscate_button_state: button::State::default(),
         }
     }
 
-    fn view(&mut self, state: &ApplicationState) -> Element<Message> {
+    fn view(&mut self, state: &ApplicationState) -> Element<'_, Message> {
         let mut content = Column::new()
             .align_items(Alignment::Center)
             .push(
-                PickList::new(&mut self.version_dropdown_state, VersionSelection::make_list(&state.versions), Some(self.selected_version.clone()),
-                              move |v| { Message::LauncherMessage(LauncherMessage::VersionSelected(v)) })
+                Container::new(
+                    self.version_list.view(state, &self.selected_version, |m| { Message::LauncherMessage(LauncherMessage::VersionList(m)) })
+                ).height(Length::FillPortion(3))
+            ).push(Space::with_height(Length::Units(5)))
+            .push(Text::new(format!("Selected: {0}", self.selected_version)).size(16))
+            .push(Space::with_height(Length::Units(10)))
+            .push(Text::new("Instance:"))
+            .push(
+                PickList::new(&mut self.instance_dropdown_state, Instance::list(&state.launcher_path), Some(self.selected_instance.clone()),
+                              move |i| { Message::LauncherMessage(LauncherMessage::InstanceSelected(i)) })
             ).push(Space::with_height(Length::Units(10)))
             .push(Text::new("Username:"))
             .push(
@@ -226,145 +534,1403 @@ impl Launcher {
                                move |s| { Message::LauncherMessage(LauncherMessage::UsernameChanged(s)) })
                 .padding(5)
                 .width(Length::Units(286))
+            ).push(Space::with_height(Length::Units(10)))
+            .push(
+                Row::new()
+                .align_items(Alignment::Center)
+                .push(
+                    Checkbox::new(self.jdwp, "Debugger on port",
+                                  move |b| { Message::LauncherMessage(LauncherMessage::JdwpToggled(b)) }).size(16).text_size(16)
+                ).push(Space::with_width(Length::Units(5)))
+                .push(
+                    TextInput::new(&mut self.jdwp_port_input_state, "", &self.jdwp_port,
+                                   move |s| { Message::LauncherMessage(LauncherMessage::JdwpPortChanged(s)) })
+                    .padding(2)
+                    .size(16)
+                    .width(Length::Units(50))
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Checkbox::new(self.jdwp_suspend, "Suspend",
+                                  move |b| { Message::LauncherMessage(LauncherMessage::JdwpSuspendToggled(b)) }).size(16).text_size(16)
+                )
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .push(
+                    Checkbox::new(self.flight_recorder, "Flight Recorder",
+                                  move |b| { Message::LauncherMessage(LauncherMessage::FlightRecorderToggled(b)) }).size(16).text_size(16)
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Checkbox::new(self.gc_logging, "GC Logging",
+                                  move |b| { Message::LauncherMessage(LauncherMessage::GcLoggingToggled(b)) }).size(16).text_size(16)
+                )
             ).push(Space::with_height(Length::FillPortion(1)));
 
-        if self.last_exit_status.is_some() {
-            content = content.push(Text::new(format!("Minecraft exited with {0}", self.last_exit_status.unwrap())));
+        if let Some(e) = &self.launch_error {
+            content = content.push(Text::new(e).color(Color::from_rgb(0.8, 0.0, 0.0)));
+        }
+        if let Some(dry_launch) = &self.dry_launch {
+            content = content.push(Text::new(format!("Wrote launch script to {0}", dry_launch.script_path)).size(14))
+                .push(Space::with_height(Length::Units(5)))
+                .push(
+                    Button::new(&mut self.copy_launch_command_button_state, Text::new("Copy Command as JSON").size(16))
+                        .on_press(Message::LauncherMessage(LauncherMessage::CopyLaunchCommandPressed))
+                );
+        }
+        if let Some(last_exit) = &self.last_exit {
+            content = content.push(Text::new(format!("Minecraft exited with {0}", last_exit.status)));
+            for output_file in last_exit.output_files.iter() {
+                content = content.push(Text::new(format!("Wrote {0}", output_file)).size(14));
+            }
+
+            // Show a summary of the crash with buttons to dig deeper
+            if let Some(crash) = &last_exit.crash {
+                let mut crash_buttons = Row::new()
+                    .push(
+                        Button::new(&mut self.open_crash_report_button_state, Text::new("Open Report").size(16))
+                            .on_press(Message::LauncherMessage(LauncherMessage::OpenCrashReportPressed))
+                    );
+                if last_exit.log_path.is_some() {
+                    crash_buttons = crash_buttons.push(Space::with_width(Length::Units(10)))
+                        .push(
+                            Button::new(&mut self.open_log_button_state, Text::new("Open Log").size(16))
+                                .on_press(Message::LauncherMessage(LauncherMessage::OpenLogPressed))
+                        );
+                }
+                crash_buttons = crash_buttons.push(Space::with_width(Length::Units(10)))
+                    .push(
+                        Button::new(&mut self.deobfuscate_button_state, Text::new("Deobfuscate").size(16))
+                            .on_press(Message::LauncherMessage(LauncherMessage::DeobfuscatePressed))
+                    );
+                content = content.push(Space::with_height(Length::Units(5)))
+                    .push(Text::new(match crash.kind {
+                        CrashKind::CrashReport => "Minecraft crashed:",
+                        CrashKind::JvmError => "Java crashed:",
+                    }).color(Color::from_rgb(0.8, 0.0, 0.0)))
+                    .push(Text::new(crash.summary()).size(14))
+                    .push(Space::with_height(Length::Units(5)))
+                    .push(crash_buttons);
+                if let Some(e) = &self.deobfuscate_error {
+                    content = content.push(Text::new(e).size(14));
+                }
+            }
+        }
+
+        // List running games with a button to kill each of them
+        let games = state.processes.list();
+        self.kill_button_states.resize_with(games.len(), button::State::default);
+        for (game, kill_button_state) in games.iter().zip(self.kill_button_states.iter_mut()) {
+            content = content.push(
+                Row::new()
+                .align_items(Alignment::Center)
+                .push(Text::new(match (game.pid(), game.uptime()) {
+                    (Some(pid), Some(uptime)) => format!("{0} ({1}), PID {2}, up {3}", game.instance, game.version, pid, format_uptime(uptime)),
+                    _ => format!("{0} ({1}), preparing to launch", game.instance, game.version),
+                }).size(16))
+                .push(Space::with_width(Length::Units(10)))
+                .push(
+                    Button::new(kill_button_state, Text::new("Kill").size(16))
+                        .on_press(Message::LauncherMessage(LauncherMessage::KillPressed(game.id)))
+                )
+            );
+        }
+
+        let launch_label;
+        if self.duplicate_warning {
+            content = content.push(Text::new(format!("Instance {0} is already running.", self.selected_instance)));
+            launch_label = "Launch Anyway";
+        }
+        else {
+            launch_label = "Launch";
         }
 
         content = content.push(Space::with_height(Length::FillPortion(1)))
             .push(
-                Button::new(&mut self.launch_button_state, Text::new("Launch"))
-                    .on_press(Message::LauncherMessage(LauncherMessage::LaunchPressed))
+                Row::new()
+                .push(
+                    Button::new(&mut self.launch_button_state, Text::new(launch_label))
+                        .on_press(Message::LauncherMessage(LauncherMessage::LaunchPressed))
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.dry_launch_button_state, Text::new("Dry Launch"))
+                        .on_press(Message::LauncherMessage(LauncherMessage::DryLaunchPressed))
+                )
             ).push(Space::with_height(Length::Units(10)));
 
         return content.into();
     }
 
+    // The version the selection refers to, falling back to the first version in the list
+    fn version(&self, state: &ApplicationState) -> MinecraftVersion {
+        return self.selected_version.version(&state.versions);
+    }
+
+    fn launch_modes(&self) -> Result<LaunchModes, String> {
+        let jdwp_port = match self.jdwp {
+            true => match self.jdwp_port.trim().parse() {
+                Ok(port) => Some(port),
+                Err(_) => return Err(format!("'{0}' isn't a valid debugger port", self.jdwp_port)),
+            },
+            false => None,
+        };
+        return Ok(LaunchModes {
+            jdwp_port: jdwp_port,
+            jdwp_suspend: self.jdwp_suspend,
+            flight_recorder: self.flight_recorder,
+            gc_logging: self.gc_logging,
+        });
+    }
+
     fn update(&mut self, state: &mut ApplicationState, message: LauncherMessage) -> Command<Message> {
         match message {
             LauncherMessage::LaunchPressed => {
-                self.last_exit_status = None;
-
-                let mut version = state.versions.versions.get(0).unwrap();
-                match &self.selected_version {
-                    VersionSelection::Latest(id) => {
-                        for v in state.versions.versions.iter() {
-                            if v.id == *id {
-                                version = v;
-                                break;
-                            }
-                        }
-                    },
-                    VersionSelection::LatestSnapshot(id) => {
-                        for v in state.versions.versions.iter() {
-                            if v.id == *id {
-                                version = v;
-                                break;
-                            }
-                        }
+                // Warn before starting a second copy of the same instance
+                if state.processes.is_running(&self.selected_instance) && !self.duplicate_warning {
+                    self.duplicate_warning = true;
+                    return Command::none();
+                }
+                self.duplicate_warning = false;
+                self.last_exit = None;
+                self.launch_error = None;
+                self.dry_launch = None;
+                self.deobfuscate_error = None;
+
+                let modes = match self.launch_modes() {
+                    Ok(modes) => modes,
+                    Err(e) => {
+                        self.launch_error = Some(e);
+                        return Command::none();
                     },
-                    VersionSelection::Version(v) => { version = &v; },
                 };
+                // Remember the player for next time
+                if let Some(account) = state.env.layer(EnvLayer::Account) {
+                    if let Err(e) = account.save(&account_path(&state.launcher_path)) {
+                        println!("{0}", e);
+                    }
+                }
 
-                return Command::perform(launch_minecraft_version(state.launcher_path.clone(), version.clone(), Box::new(state.env.clone())),
+                let version = self.version(state);
+                let instance = match Instance::load(&state.launcher_path, &self.selected_instance) {
+                    Ok(instance) => instance,
+                    Err(e) => {
+                        self.launch_error = Some(e);
+                        return Command::none();
+                    },
+                };
+                state.session_log = Arc::new(SessionLog::new(Some(version.id.clone())));
+                return Command::perform(launch_minecraft_version(state.launcher_path.clone(), version, instance, modes, Box::new(state.env.clone()), state.session_log.clone(), state.processes.clone()),
                                         move |s| { Message::LauncherMessage(LauncherMessage::MinecraftExited(s)) });
             },
-            LauncherMessage::VersionSelected(version) => {
-                self.selected_version = version;
+            LauncherMessage::DryLaunchPressed => {
+                self.launch_error = None;
+                self.dry_launch = None;
+                let modes = match self.launch_modes() {
+                    Ok(modes) => modes,
+                    Err(e) => {
+                        self.launch_error = Some(e);
+                        return Command::none();
+                    },
+                };
+                let version = self.version(state);
+                let instance = match Instance::load(&state.launcher_path, &self.selected_instance) {
+                    Ok(instance) => instance,
+                    Err(e) => {
+                        self.launch_error = Some(e);
+                        return Command::none();
+                    },
+                };
+                return Command::perform(dry_launch_minecraft_version(state.launcher_path.clone(), version, instance, modes, Box::new(state.env.clone())),
+                                        |r| { Message::LauncherMessage(LauncherMessage::DryLaunchFinished(r)) });
+            },
+            LauncherMessage::DryLaunchFinished(result) => {
+                match result {
+                    Ok(dry_launch) => self.dry_launch = Some(dry_launch),
+                    Err(e) => self.launch_error = Some(e),
+                }
+            },
+            LauncherMessage::CopyLaunchCommandPressed => {
+                if let Some(dry_launch) = &self.dry_launch {
+                    return clipboard::write(dry_launch.command.to_json());
+                }
+            },
+            LauncherMessage::JdwpToggled(jdwp) => {
+                self.jdwp = jdwp;
+            },
+            LauncherMessage::JdwpPortChanged(port) => {
+                self.jdwp_port = port;
+            },
+            LauncherMessage::JdwpSuspendToggled(suspend) => {
+                self.jdwp_suspend = suspend;
+            },
+            LauncherMessage::FlightRecorderToggled(flight_recorder) => {
+                self.flight_recorder = flight_recorder;
+            },
+            LauncherMessage::GcLoggingToggled(gc_logging) => {
+                self.gc_logging = gc_logging;
+            },
+            LauncherMessage::VersionList(list_message) => {
+                if let Some(selection) = self.version_list.update(list_message) {
+                    self.selected_version = selection;
+                }
+            },
+            LauncherMessage::InstanceSelected(instance) => {
+                self.selected_instance = instance;
+                self.duplicate_warning = false;
             },
             LauncherMessage::UsernameChanged(username) => {
                 self.username = username;
-                state.env.set("auth_player_name", &self.username);
+                state.env.layer_mut(EnvLayer::Account).set("auth_player_name", &self.username);
+            }
+            LauncherMessage::MinecraftExited(result) => {
+                match result {
+                    Ok(game_exit) => self.last_exit = Some(game_exit),
+                    Err(e) => self.launch_error = Some(e),
+                }
+            }
+            LauncherMessage::KillPressed(id) => {
+                if let Some(game) = state.processes.get(id) {
+                    game.kill();
+                }
+            }
+            LauncherMessage::OpenCrashReportPressed => {
+                if let Some(crash) = self.last_exit.as_ref().and_then(|e| e.crash.as_ref()) {
+                    open_path(&crash.path);
+                }
+            }
+            LauncherMessage::OpenLogPressed => {
+                if let Some(log_path) = self.last_exit.as_ref().and_then(|e| e.log_path.as_ref()) {
+                    open_path(log_path);
+                }
             }
-            LauncherMessage::MinecraftExited(status) => {
-                self.last_exit_status = Some(status);
+            LauncherMessage::DeobfuscatePressed => {
+                if let Some(last_exit) = &self.last_exit {
+                    if let Some(crash) = &last_exit.crash {
+                        return Command::perform(deobfuscate_file(state.launcher_path.clone(), last_exit.version.clone(), crash.path.clone()),
+                                                |r| { Message::LauncherMessage(LauncherMessage::DeobfuscateFinished(r)) });
+                    }
+                }
             }
+            LauncherMessage::DeobfuscateFinished(result) => {
+                match result {
+                    Ok(path) => {
+                        self.deobfuscate_error = None;
+                        open_path(&path);
+                    },
+                    Err(e) => self.deobfuscate_error = Some(e),
+                }
+            }
+            LauncherMessage::Tick => {}
         }
         return Command::none();
     }
 }
 
+#[derive(Debug, Clone)]
+enum InstanceMessage {
+    InstanceSelected(String),
+    NewNameChanged(String),
+    CreatePressed,
+    MinMemoryChanged(String),
+    MaxMemoryChanged(String),
+    SuggestMemoryPressed,
+    JvmArgsChanged(String),
+    GameArgsChanged(String),
+    WrapperCommandChanged(String),
+    EnvVarsChanged(String),
+    UnsetEnvVarsChanged(String),
+    PreLaunchHookChanged(String),
+    PostExitHookChanged(String),
+    LoggingConfigChanged(String),
+    SavePressed,
+    ShowVariablesToggled,
+}
+
+// Settings are edited as text and only parsed when saving
+struct InstanceEditor {
+    instance: Instance,
+    new_name: String,
+    min_memory: String,
+    max_memory: String,
+    jvm_args: String,
+    game_args: String,
+    wrapper_command: String,
+    env_vars: String,
+    unset_env_vars: String,
+    pre_launch_hook: String,
+    post_exit_hook: String,
+    logging_config: String,
+    status: Option<String>,
+    // Where each launcher variable comes from for this instance, if shown
+    variables: Option<Vec<String>>,
+
+    instance_dropdown_state: pick_list::State<String>,
+    new_name_input_state: text_input::State,
+    create_button_state: button::State,
+    min_memory_input_state: text_input::State,
+    max_memory_input_state: text_input::State,
+    suggest_memory_button_state: button::State,
+    jvm_args_input_state: text_input::State,
+    game_args_input_state: text_input::State,
+    wrapper_command_input_state: text_input::State,
+    env_vars_input_state: text_input::State,
+    unset_env_vars_input_state: text_input::State,
+    pre_launch_hook_input_state: text_input::State,
+    post_exit_hook_input_state: text_input::State,
+    logging_config_input_state: text_input::State,
+    save_button_state: button::State,
+    show_variables_button_state: button::State,
+    settings_scroll_state: scrollable::State,
+}
+
+impl InstanceEditor {
+    fn new(state: &ApplicationState) -> Self {
+        let mut editor = InstanceEditor {
+            instance: Instance::new(&state.launcher_path, "default"),
+            new_name: String::from(""),
+            min_memory: String::from(""),
+            max_memory: String::from(""),
+            jvm_args: String::from(""),
+            game_args: String::from(""),
+            wrapper_command: String::from(""),
+            env_vars: String::from(""),
+            unset_env_vars: String::from(""),
+            pre_launch_hook: String::from(""),
+            post_exit_hook: String::from(""),
+            logging_config: String::from(""),
+            status: None,
+            variables: None,
+
+            instance_dropdown_state: pick_list::State::default(),
+            new_name_input_state: text_input::State::default(),
+            create_button_state: button::State::default(),
+            min_memory_input_state: text_input::State::default(),
+            max_memory_input_state: text_input::State::default(),
+            suggest_memory_button_state: button::State::default(),
+            jvm_args_input_state: text_input::State::default(),
+            game_args_input_state: text_input::State::default(),
+            wrapper_command_input_state: text_input::State::default(),
+            env_vars_input_state: text_input::State::default(),
+            unset_env_vars_input_state: text_input::State::default(),
+            pre_launch_hook_input_state: text_input::State::default(),
+            post_exit_hook_input_state: text_input::State::default(),
+            logging_config_input_state: text_input::State::default(),
+            save_button_state: button::State::default(),
+            show_variables_button_state: button::State::default(),
+            settings_scroll_state: scrollable::State::default(),
+        };
+        editor.load_instance(state, "default");
+        return editor;
+    }
+
+    // A broken instance.json is shown as an error, with the default settings loaded in its place
+    fn load_instance(&mut self, state: &ApplicationState, name: &str) {
+        self.status = None;
+        self.instance = match Instance::load(&state.launcher_path, name) {
+            Ok(instance) => instance,
+            Err(e) => {
+                self.status = Some(format!("{0}, saving will replace it", e));
+                Instance::new(&state.launcher_path, name)
+            },
+        };
+        self.load_fields();
+    }
+
+    fn load_fields(&mut self) {
+        let settings = &self.instance.settings;
+        self.min_memory = settings.min_memory.map_or(String::new(), |m| m.to_string());
+        self.max_memory = settings.max_memory.map_or(String::new(), |m| m.to_string());
+        self.jvm_args = join_args(&settings.jvm_args);
+        self.game_args = join_args(&settings.game_args);
+        self.wrapper_command = join_args(&settings.wrapper_command);
+        let env_vars: Vec<String> = settings.env_vars.iter().map(|(name, value)| format!("{0}={1}", name, value)).collect();
+        self.env_vars = join_args(&env_vars);
+        self.unset_env_vars = join_args(&settings.unset_env_vars);
+        self.pre_launch_hook = settings.pre_launch_hook.clone().unwrap_or_default();
+        self.post_exit_hook = settings.post_exit_hook.clone().unwrap_or_default();
+        self.logging_config = settings.logging_config.clone().unwrap_or_default();
+    }
+
+    fn load_variables(&mut self, state: &ApplicationState) {
+        let mut env = state.env.clone();
+        let mut variables = Vec::new();
+        match self.instance.load_environment() {
+            Ok(instance_env) => env.set_layer(EnvLayer::Instance, instance_env),
+            Err(e) => variables.push(e),
+        }
+        variables.extend(env.describe());
+        self.variables = Some(variables);
+    }
+
+    fn parse_memory(field: &str, memory: &str) -> Result<Option<u32>, String> {
+        if memory.trim().is_empty() {
+            return Ok(None);
+        }
+        match memory.trim().parse::<u32>() {
+            Ok(m) => Ok(Some(m)),
+            Err(_) => Err(format!("{0} must be a number of megabytes", field)),
+        }
+    }
+
+    fn optional(field: &str) -> Option<String> {
+        match field.trim() {
+            "" => None,
+            value => Some(value.to_string()),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let min_memory = InstanceEditor::parse_memory("Minimum memory", &self.min_memory)?;
+        let max_memory = InstanceEditor::parse_memory("Maximum memory", &self.max_memory)?;
+        if let (Some(min), Some(max)) = (min_memory, max_memory) {
+            if min > max {
+                return Err(String::from("Minimum memory can't be more than the maximum"));
+            }
+        }
+
+        let mut env_vars = BTreeMap::new();
+        for env_var in split_args(&self.env_vars) {
+            match env_var.split_once('=') {
+                Some((name, value)) if !name.is_empty() => {
+                    env_vars.insert(name.to_string(), value.to_string());
+                },
+                _ => return Err(format!("Environment variable '{0}' should look like NAME=value", env_var)),
+            }
+        }
+
+        let settings = &mut self.instance.settings;
+        // Launching records the version, which may have happened since the editor loaded the instance
+        if let Ok(saved) = Instance::load_settings(&self.instance.path) {
+            settings.last_version = saved.last_version;
+        }
+        settings.env_vars = env_vars;
+        settings.unset_env_vars = split_args(&self.unset_env_vars);
+        settings.pre_launch_hook = InstanceEditor::optional(&self.pre_launch_hook);
+        settings.post_exit_hook = InstanceEditor::optional(&self.post_exit_hook);
+        settings.min_memory = min_memory;
+        settings.max_memory = max_memory;
+        settings.jvm_args = split_args(&self.jvm_args);
+        settings.game_args = split_args(&self.game_args);
+        settings.wrapper_command = split_args(&self.wrapper_command);
+        settings.logging_config = InstanceEditor::optional(&self.logging_config);
+        self.instance.save();
+        return Ok(());
+    }
+
+    fn text_field<'a>(label: &str, input_state: &'a mut text_input::State, placeholder: &str, value: &str,
+                      message: fn(String) -> InstanceMessage) -> Column<'a, Message> {
+        return Column::new()
+            .push(Text::new(label).size(16))
+            .push(
+                TextInput::new(input_state, placeholder, value, move |s| { Message::InstanceMessage(message(s)) })
+                .padding(5)
+                .size(16)
+            ).push(Space::with_height(Length::Units(5)));
+    }
+
+    fn view(&mut self, state: &ApplicationState) -> Element<'_, Message> {
+        let max_memory_placeholder = match suggested_max_memory() {
+            Some(m) => format!("Suggested: {0}", m),
+            None => String::from("Java default"),
+        };
+
+        let mut settings = Scrollable::new(&mut self.settings_scroll_state)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(5)
+            .push(InstanceEditor::text_field("Minimum memory (MB):", &mut self.min_memory_input_state, "Java default", &self.min_memory,
+                                             InstanceMessage::MinMemoryChanged))
+            .push(
+                Row::new()
+                .align_items(Alignment::End)
+                .push(InstanceEditor::text_field("Maximum memory (MB):", &mut self.max_memory_input_state, &max_memory_placeholder, &self.max_memory,
+                                                 InstanceMessage::MaxMemoryChanged).width(Length::Fill))
+                .push(Space::with_width(Length::Units(10)))
+                .push(
+                    Column::new()
+                    .push(
+                        Button::new(&mut self.suggest_memory_button_state, Text::new("Suggest").size(16))
+                            .on_press(Message::InstanceMessage(InstanceMessage::SuggestMemoryPressed))
+                    ).push(Space::with_height(Length::Units(5)))
+                )
+            ).push(InstanceEditor::text_field("Extra JVM arguments:", &mut self.jvm_args_input_state, "ie -XX:+UseG1GC", &self.jvm_args,
+                                              InstanceMessage::JvmArgsChanged))
+            .push(InstanceEditor::text_field("Extra game arguments:", &mut self.game_args_input_state, "ie --fullscreen", &self.game_args,
+                                             InstanceMessage::GameArgsChanged))
+            .push(InstanceEditor::text_field("Wrapper command:", &mut self.wrapper_command_input_state, "ie gamemoderun", &self.wrapper_command,
+                                             InstanceMessage::WrapperCommandChanged))
+            .push(InstanceEditor::text_field("Environment variables:", &mut self.env_vars_input_state, "ie MESA_GL_VERSION_OVERRIDE=4.5", &self.env_vars,
+                                             InstanceMessage::EnvVarsChanged))
+            .push(InstanceEditor::text_field("Unset environment variables:", &mut self.unset_env_vars_input_state, "ie _JAVA_OPTIONS", &self.unset_env_vars,
+                                             InstanceMessage::UnsetEnvVarsChanged))
+            .push(InstanceEditor::text_field("Pre-launch hook:", &mut self.pre_launch_hook_input_state, "ie git pull", &self.pre_launch_hook,
+                                             InstanceMessage::PreLaunchHookChanged))
+            .push(InstanceEditor::text_field("Post-exit hook:", &mut self.post_exit_hook_input_state, "ie ./backup-saves.sh", &self.post_exit_hook,
+                                             InstanceMessage::PostExitHookChanged))
+            .push(InstanceEditor::text_field("Logging config:", &mut self.logging_config_input_state, "Use the version's config", &self.logging_config,
+                                             InstanceMessage::LoggingConfigChanged));
+        if let Some(variables) = &self.variables {
+            settings = settings.push(Text::new("Variables:"))
+                .push(Text::new("Variables set while launching, like classpath, aren't shown").size(14));
+            for variable in variables.iter() {
+                settings = settings.push(Text::new(variable).size(14));
+            }
+        }
+
+        let mut content = Column::new()
+            .align_items(Alignment::Center)
+            .push(
+                PickList::new(&mut self.instance_dropdown_state, Instance::list(&state.launcher_path), Some(self.instance.name.clone()),
+                              move |i| { Message::InstanceMessage(InstanceMessage::InstanceSelected(i)) })
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .align_items(Alignment::Center)
+                .push(
+                    TextInput::new(&mut self.new_name_input_state, "New instance name...", &self.new_name,
+                                   move |s| { Message::InstanceMessage(InstanceMessage::NewNameChanged(s)) })
+                    .padding(5)
+                    .width(Length::Fill)
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.create_button_state, Text::new("Create"))
+                        .on_press(Message::InstanceMessage(InstanceMessage::CreatePressed))
+                )
+            ).push(Space::with_height(Length::Units(5)))
+            .push(settings);
+
+        if let Some(status) = &self.status {
+            content = content.push(Text::new(status).size(16));
+        }
+
+        let variables_label = match self.variables {
+            Some(_) => "Hide Variables",
+            None => "Show Variables",
+        };
+        content = content.push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .push(
+                    Button::new(&mut self.save_button_state, Text::new("Save"))
+                        .on_press(Message::InstanceMessage(InstanceMessage::SavePressed))
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.show_variables_button_state, Text::new(variables_label))
+                        .on_press(Message::InstanceMessage(InstanceMessage::ShowVariablesToggled))
+                )
+            ).push(Space::with_height(Length::Units(10)))
+            .padding(10);
+        return content.into();
+    }
+
+    fn update(&mut self, state: &mut ApplicationState, message: InstanceMessage) -> Command<Message> {
+        match message {
+            InstanceMessage::InstanceSelected(name) => {
+                self.load_instance(state, &name);
+                if self.variables.is_some() {
+                    self.load_variables(state);
+                }
+            },
+            InstanceMessage::NewNameChanged(name) => {
+                self.new_name = name;
+            },
+            InstanceMessage::CreatePressed => {
+                if !Instance::is_valid_name(&self.new_name) {
+                    self.status = Some(format!("'{0}' isn't a valid instance name", self.new_name));
+                }
+                else {
+                    let name = self.new_name.trim().to_string();
+                    self.load_instance(state, &name);
+                    if self.status.is_none() {
+                        self.instance.save();
+                        self.status = Some(format!("Created instance {0}", self.instance.name));
+                    }
+                    self.new_name = String::from("");
+                }
+            },
+            InstanceMessage::MinMemoryChanged(memory) => {
+                self.min_memory = memory;
+            },
+            InstanceMessage::MaxMemoryChanged(memory) => {
+                self.max_memory = memory;
+            },
+            InstanceMessage::SuggestMemoryPressed => {
+                match suggested_max_memory() {
+                    Some(m) => self.max_memory = m.to_string(),
+                    None => self.status = Some(String::from("Couldn't find the amount of system memory")),
+                }
+            },
+            InstanceMessage::JvmArgsChanged(args) => {
+                self.jvm_args = args;
+            },
+            InstanceMessage::GameArgsChanged(args) => {
+                self.game_args = args;
+            },
+            InstanceMessage::WrapperCommandChanged(command) => {
+                self.wrapper_command = command;
+            },
+            InstanceMessage::EnvVarsChanged(env_vars) => {
+                self.env_vars = env_vars;
+            },
+            InstanceMessage::UnsetEnvVarsChanged(env_vars) => {
+                self.unset_env_vars = env_vars;
+            },
+            InstanceMessage::PreLaunchHookChanged(hook) => {
+                self.pre_launch_hook = hook;
+            },
+            InstanceMessage::PostExitHookChanged(hook) => {
+                self.post_exit_hook = hook;
+            },
+            InstanceMessage::LoggingConfigChanged(path) => {
+                self.logging_config = path;
+            },
+            InstanceMessage::SavePressed => {
+                self.status = match self.save() {
+                    Ok(()) => Some(format!("Saved instance {0}", self.instance.name)),
+                    Err(e) => Some(e),
+                };
+            },
+            InstanceMessage::ShowVariablesToggled => {
+                match self.variables {
+                    Some(_) => self.variables = None,
+                    None => self.load_variables(state),
+                }
+            },
+        }
+        return Command::none();
+    }
+}
+
+// Kinds of versions the version list can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VersionFilter {
+    Release,
+    Snapshot,
+    Beta,
+    Alpha,
+    // Installed versions that aren't in the version list, like modded profiles
+    Custom,
+    InstalledOnly,
+}
+
+#[derive(Debug, Clone)]
+enum VersionListMessage {
+    SearchChanged(String),
+    FilterToggled(VersionFilter, bool),
+    SortPressed,
+    Selected(VersionSelection),
+}
+
+// Scrollable list of versions that can be searched, filtered by type and sorted by release date
+struct VersionList {
+    search: String,
+    releases: bool,
+    snapshots: bool,
+    betas: bool,
+    alphas: bool,
+    custom: bool,
+    installed_only: bool,
+    newest_first: bool,
+
+    search_input_state: text_input::State,
+    sort_button_state: button::State,
+    list_scroll_state: scrollable::State,
+    row_button_states: Vec<button::State>,
+}
+
+impl VersionList {
+    // Every version is its own widget, so only this many are shown before needing to narrow it down
+    const MAX_SHOWN_VERSIONS: usize = 200;
+
+    fn new() -> Self {
+        VersionList {
+            search: String::new(),
+            releases: true,
+            snapshots: false,
+            betas: false,
+            alphas: false,
+            custom: true,
+            installed_only: false,
+            newest_first: true,
+
+            search_input_state: text_input::State::default(),
+            sort_button_state: button::State::default(),
+            list_scroll_state: scrollable::State::default(),
+            row_button_states: Vec::new(),
+        }
+    }
+
+    // Versions matching the search and filters, sorted by release date
+    // The latest release and snapshot come first while nothing is being searched for
+    fn entries(&self, state: &ApplicationState) -> Vec<VersionSelection> {
+        let search = self.search.trim().to_lowercase();
+        let mut versions: Vec<&MinecraftVersion> = Vec::new();
+        for v in state.versions.versions.iter() {
+            let shown_type = match v.version_type.as_str() {
+                "release" => self.releases,
+                "snapshot" => self.snapshots,
+                "old_beta" => self.betas,
+                "old_alpha" => self.alphas,
+                _ => false,
+            };
+            if shown_type {
+                versions.push(v);
+            }
+        }
+        if self.custom {
+            versions.extend(state.custom_versions.iter());
+        }
+        versions.retain(|v| v.id.to_lowercase().contains(&search) && (!self.installed_only || state.installed_versions.contains(&v.id)));
+        versions.sort_by(|a, b| a.release_time.cmp(&b.release_time));
+        if self.newest_first {
+            versions.reverse();
+        }
+
+        let mut entries = Vec::new();
+        if search.is_empty() && !self.installed_only {
+            if self.releases {
+                entries.push(VersionSelection::Latest(state.versions.latest.release.clone()));
+            }
+            if self.snapshots {
+                entries.push(VersionSelection::LatestSnapshot(state.versions.latest.snapshot.clone()));
+            }
+        }
+        entries.extend(versions.into_iter().map(|v| VersionSelection::Version(v.clone())));
+        return entries;
+    }
+
+    fn view<'a>(&'a mut self, state: &ApplicationState, selected: &VersionSelection, on_message: fn(VersionListMessage) -> Message) -> Element<'a, Message> {
+        let entries = self.entries(state);
+        let shown = entries.len().min(VersionList::MAX_SHOWN_VERSIONS);
+        self.row_button_states.resize_with(shown, button::State::default);
+
+        let filter_checkbox = |checked: bool, label: &str, filter: VersionFilter| {
+            Checkbox::new(checked, label, move |b| { on_message(VersionListMessage::FilterToggled(filter, b)) }).size(14).text_size(14)
+        };
+        let sort_label = if self.newest_first { "Newest first" } else { "Oldest first" };
+        let mut content = Column::new()
+            .align_items(Alignment::Center)
+            .push(
+                TextInput::new(&mut self.search_input_state, "Search versions...", &self.search,
+                               move |s| { on_message(VersionListMessage::SearchChanged(s)) })
+                .padding(5)
+                .size(16)
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .spacing(10)
+                .push(filter_checkbox(self.releases, "Release", VersionFilter::Release))
+                .push(filter_checkbox(self.snapshots, "Snapshot", VersionFilter::Snapshot))
+                .push(filter_checkbox(self.betas, "Beta", VersionFilter::Beta))
+                .push(filter_checkbox(self.alphas, "Alpha", VersionFilter::Alpha))
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(filter_checkbox(self.custom, "Custom", VersionFilter::Custom))
+                .push(filter_checkbox(self.installed_only, "Installed only", VersionFilter::InstalledOnly))
+                .push(
+                    Button::new(&mut self.sort_button_state, Text::new(sort_label).size(14))
+                        .on_press(on_message(VersionListMessage::SortPressed))
+                )
+            ).push(Space::with_height(Length::Units(5)));
+
+        let mut list = Scrollable::new(&mut self.list_scroll_state)
+            .width(Length::Fill)
+            .height(Length::Fill);
+        for (entry, button_state) in entries.iter().zip(self.row_button_states.iter_mut()) {
+            let version = entry.version(&state.versions);
+            let (name, version_type) = match entry {
+                VersionSelection::Latest(id) => (format!("Latest Release ({0})", id), String::new()),
+                VersionSelection::LatestSnapshot(id) => (format!("Latest Snapshot ({0})", id), String::new()),
+                VersionSelection::Version(v) => (v.id.clone(), v.version_type.clone()),
+            };
+            let installed = if state.installed_versions.contains(&version.id) { "Installed" } else { "" };
+            let color = if entry == selected { Color::from_rgb(0.0, 0.4, 0.8) } else { Color::BLACK };
+            let row = Row::new()
+                .push(Text::new(name).size(16).color(color).width(Length::FillPortion(3)))
+                .push(Text::new(version_type).size(14).color(color).width(Length::FillPortion(1)))
+                .push(Text::new(version.release_time.get(..10).unwrap_or_default()).size(14).color(color).width(Length::FillPortion(1)))
+                .push(Text::new(installed).size(14).color(color).width(Length::FillPortion(1)));
+            list = list.push(
+                Button::new(button_state, row)
+                    .width(Length::Fill)
+                    .padding(2)
+                    .on_press(on_message(VersionListMessage::Selected(entry.clone())))
+            );
+        }
+        content = content.push(list);
+        if entries.len() > shown {
+            content = content.push(Text::new(format!("Showing {0} of {1} versions, search to narrow them down", shown, entries.len())).size(14));
+        }
+        else if entries.is_empty() {
+            content = content.push(Text::new("No versions match").size(14));
+        }
+        return content.into();
+    }
+
+    // Returns the version that was picked, if one was
+    fn update(&mut self, message: VersionListMessage) -> Option<VersionSelection> {
+        match message {
+            VersionListMessage::SearchChanged(search) => {
+                self.search = search;
+                self.list_scroll_state.snap_to(0.0);
+            },
+            VersionListMessage::FilterToggled(filter, checked) => {
+                match filter {
+                    VersionFilter::Release => self.releases = checked,
+                    VersionFilter::Snapshot => self.snapshots = checked,
+                    VersionFilter::Beta => self.betas = checked,
+                    VersionFilter::Alpha => self.alphas = checked,
+                    VersionFilter::Custom => self.custom = checked,
+                    VersionFilter::InstalledOnly => self.installed_only = checked,
+                }
+                self.list_scroll_state.snap_to(0.0);
+            },
+            VersionListMessage::SortPressed => {
+                self.newest_first = !self.newest_first;
+            },
+            VersionListMessage::Selected(selection) => return Some(selection),
+        }
+        return None;
+    }
+}
+
 #[derive(Debug, Clone)]
 enum DownloaderMessage {
-    VersionSelected(VersionSelection),
+    VersionList(VersionListMessage),
+    DeepVerifyToggled(bool),
+    AssetLinksSelected(AssetLinkMode),
+    RemoveOrphanedToggled(bool),
     DownloadPressed,
-    DownloadFinished(String),
+    DownloadFinished(Result<String, String>),
+    // Whether to repair what's found
+    VerifyPressed(bool),
+    // The report from verifying, and the one from after repairing if it was repaired
+    VerifyFinished(Result<(VerifyReport, Option<VerifyReport>), String>),
+    UninstallPressed,
+    UninstallFinished(String, Result<PruneReport, String>),
+    ReinstallPressed,
+    ReinstallFinished(Result<String, String>),
+    // Version ID, size of its directory, and what uninstalling it with its orphaned data would free
+    DiskUsageLoaded(String, u64, u64),
 }
 
 struct Downloader {
     selected_version: VersionSelection,
-    last_downloaded_id: Option<String>,
+    // Hash every file instead of trusting the hash cache
+    deep_verify: bool,
+    // Also remove libraries and assets nothing else uses when uninstalling
+    remove_orphaned: bool,
+    // What's currently being done, ie "Downloading 1.19.2..."
+    progress: Option<String>,
+    status: Option<String>,
+    error: Option<String>,
+    verify_result: Option<Result<(VerifyReport, Option<VerifyReport>), String>>,
+    disk_usage: Option<(String, u64, u64)>,
+    version_list: VersionList,
 
-    version_dropdown_state: pick_list::State<VersionSelection>,
+    asset_links_dropdown_state: pick_list::State<AssetLinkMode>,
     download_button_state: button::State,
+    verify_button_state: button::State,
+    repair_button_state: button::State,
+    uninstall_button_state: button::State,
+    reinstall_button_state: button::State,
 }
 
 impl Downloader {
+    const MAX_SHOWN_FINDINGS: usize = 8;
+
     fn new(state: &ApplicationState) -> Self {
         Downloader {
             selected_version: VersionSelection::Latest(state.versions.latest.release.clone()),
-            last_downloaded_id: None,
+            deep_verify: false,
+            remove_orphaned: false,
+            progress: None,
+            status: None,
+            error: None,
+            verify_result: None,
+            disk_usage: None,
+            version_list: VersionList::new(),
 
-            version_dropdown_state: pick_list::State::default(),
+            asset_links_dropdown_state: pick_list::State::default(),
             download_button_state: button::State::default(),
+            verify_button_state: button::State::default(),
+            repair_button_state: button::State::default(),
+            uninstall_button_state: button::State::default(),
+            reinstall_button_state: button::State::default(),
+        }
+    }
+
+    // Works out the disk usage in the background, since it reads the asset indexes of every installed version
+    fn load_disk_usage(&self, state: &ApplicationState) -> Command<Message> {
+        let id = self.selected_version.version(&state.versions).id;
+        if !state.installed_versions.contains(&id) {
+            return Command::none();
         }
+        let minecraft_path = state.launcher_path.clone();
+        return Command::perform(async move {
+            let (version_size, freed) = version_disk_usage(&minecraft_path, &id);
+            (id, version_size, freed)
+        }, |(id, version_size, freed)| { Message::DownloaderMessage(DownloaderMessage::DiskUsageLoaded(id, version_size, freed)) });
     }
 
-    fn view(&mut self, state: &ApplicationState) -> Element<Message> {
+    // Starts an operation on the selected version, clearing out the results of the last one
+    fn start(&mut self, progress: String) {
+        self.progress = Some(progress);
+        self.status = None;
+        self.error = None;
+        self.verify_result = None;
+    }
+
+    fn view(&mut self, state: &ApplicationState) -> Element<'_, Message> {
+        let version = self.selected_version.version(&state.versions);
+        let id = version.id.clone();
+        let installed = state.installed_versions.contains(&id);
         let mut content = Column::new()
             .align_items(Alignment::Center)
             .push(
-                PickList::new(&mut self.version_dropdown_state, VersionSelection::make_list(&state.versions), Some(self.selected_version.clone()),
-                              move |v| { Message::DownloaderMessage(DownloaderMessage::VersionSelected(v)) })
-            ).push(Space::with_height(Length::FillPortion(1)));
+                Container::new(
+                    self.version_list.view(state, &self.selected_version, |m| { Message::DownloaderMessage(DownloaderMessage::VersionList(m)) })
+                ).height(Length::FillPortion(4))
+            ).push(Space::with_height(Length::Units(5)))
+            .push(Text::new(format!("Selected: {0}", self.selected_version)).size(16));
+
+        if let Some((usage_id, version_size, freed)) = &self.disk_usage {
+            if installed && *usage_id == id {
+                content = content.push(Space::with_height(Length::Units(5)))
+                    .push(Text::new(format!("Uses {0}, uninstalling with unused data frees {1}", format_size(*version_size), format_size(*freed))).size(16));
+            }
+        }
+        content = content.push(Space::with_height(Length::FillPortion(1)));
+
+        if let Some(progress) = &self.progress {
+            content = content.push(Text::new(progress).size(16));
+        }
+        if let Some(status) = &self.status {
+            content = content.push(Text::new(status).size(16));
+        }
+        if let Some(e) = &self.error {
+            content = content.push(Text::new(e).color(Color::from_rgb(0.8, 0.0, 0.0)));
+        }
+        match &self.verify_result {
+            Some(Ok((report, repaired))) => {
+                // Only the first few findings fit, the CLI lists them all
+                let shown = repaired.as_ref().unwrap_or(report);
+                if repaired.is_some() {
+                    content = content.push(Text::new(format!("Before repairing: {0}", report.summary())).size(16));
+                }
+                content = content.push(Text::new(shown.summary()).size(16));
+                for line in shown.lines().iter().take(Downloader::MAX_SHOWN_FINDINGS) {
+                    content = content.push(Text::new(line).size(14));
+                }
+                if shown.findings.len() > Downloader::MAX_SHOWN_FINDINGS {
+                    content = content.push(Text::new(format!("...and {0} more", shown.findings.len() - Downloader::MAX_SHOWN_FINDINGS)).size(14));
+                }
+            },
+            Some(Err(e)) => {
+                content = content.push(Text::new(e).color(Color::from_rgb(0.8, 0.0, 0.0)));
+            },
+            None => (),
+        }
 
-        if let Some(id) = &self.last_downloaded_id {
-            content = content.push(Text::new(format!("Version {0} finished downloading.", id)));
+        // Only one thing happens to a version at a time
+        let idle = self.progress.is_none();
+        let mut download_button = Button::new(&mut self.download_button_state, Text::new("Download"));
+        let mut verify_button = Button::new(&mut self.verify_button_state, Text::new("Verify"));
+        let mut repair_button = Button::new(&mut self.repair_button_state, Text::new("Repair"));
+        let mut uninstall_button = Button::new(&mut self.uninstall_button_state, Text::new("Uninstall"));
+        let mut reinstall_button = Button::new(&mut self.reinstall_button_state, Text::new("Reinstall"));
+        // Custom versions can't be downloaded again, so they can only be verified and uninstalled
+        let custom = version.is_custom();
+        if idle {
+            verify_button = verify_button.on_press(Message::DownloaderMessage(DownloaderMessage::VerifyPressed(false)));
+        }
+        if idle && !custom {
+            download_button = download_button.on_press(Message::DownloaderMessage(DownloaderMessage::DownloadPressed));
+            repair_button = repair_button.on_press(Message::DownloaderMessage(DownloaderMessage::VerifyPressed(true)));
+        }
+        if idle && installed {
+            uninstall_button = uninstall_button.on_press(Message::DownloaderMessage(DownloaderMessage::UninstallPressed));
+        }
+        if idle && installed && !custom {
+            reinstall_button = reinstall_button.on_press(Message::DownloaderMessage(DownloaderMessage::ReinstallPressed));
         }
 
         content = content.push(Space::with_height(Length::FillPortion(1)))
             .push(
-                Button::new(&mut self.download_button_state, Text::new("Download"))
-                    .on_press(Message::DownloaderMessage(DownloaderMessage::DownloadPressed))
+                Checkbox::new(self.deep_verify, "Deep verify",
+                              move |b| { Message::DownloaderMessage(DownloaderMessage::DeepVerifyToggled(b)) }).size(16).text_size(16)
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Checkbox::new(self.remove_orphaned, "Uninstall unused libraries and assets too",
+                              move |b| { Message::DownloaderMessage(DownloaderMessage::RemoveOrphanedToggled(b)) }).size(16).text_size(16)
+            ).push(Space::with_height(Length::Units(5)))
+            .push(Text::new("Versions are only protected by instances that have been launched since this launcher started recording them").size(14))
+            .push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Old version assets:").size(16))
+                .push(Space::with_width(Length::Units(10)))
+                .push(
+                    PickList::new(&mut self.asset_links_dropdown_state, &AssetLinkMode::ALL[..], Some(state.config.asset_links),
+                                  move |m| { Message::DownloaderMessage(DownloaderMessage::AssetLinksSelected(m)) }).text_size(16)
+                )
+            ).push(Space::with_height(Length::Units(10)))
+            .push(
+                Row::new()
+                .push(download_button)
+                .push(Space::with_width(Length::Units(10)))
+                .push(verify_button)
+                .push(Space::with_width(Length::Units(10)))
+                .push(repair_button)
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .push(uninstall_button)
+                .push(Space::with_width(Length::Units(10)))
+                .push(reinstall_button)
             ).push(Space::with_height(Length::Units(10)));
         return content.into();
     }
 
     fn update(&mut self, state: &mut ApplicationState, message: DownloaderMessage) -> Command<Message> {
         match message {
-            DownloaderMessage::VersionSelected(version) => {
-                self.selected_version = version;
+            DownloaderMessage::VersionList(list_message) => {
+                if let Some(selection) = self.version_list.update(list_message) {
+                    self.selected_version = selection;
+                    return self.load_disk_usage(state);
+                }
+            },
+            DownloaderMessage::DeepVerifyToggled(deep_verify) => {
+                self.deep_verify = deep_verify;
+            },
+            DownloaderMessage::AssetLinksSelected(mode) => {
+                state.config.asset_links = mode;
+                self.error = state.config.save(&state.launcher_path).err();
+            },
+            DownloaderMessage::RemoveOrphanedToggled(remove_orphaned) => {
+                self.remove_orphaned = remove_orphaned;
             },
             DownloaderMessage::DownloadPressed => {
-                let mut version = state.versions.versions.get(0).unwrap();
-                match &self.selected_version {
-                    VersionSelection::Latest(id) => {
-                        for v in state.versions.versions.iter() {
-                            if v.id == *id {
-                                version = v;
-                                break;
-                            }
-                        }
-                    },
-                    VersionSelection::LatestSnapshot(id) => {
-                        for v in state.versions.versions.iter() {
-                            if v.id == *id {
-                                version = v;
-                                break;
-                            }
+                let version = self.selected_version.version(&state.versions);
+                self.start(format!("Downloading {0}...", version.id));
+                return Command::perform(download_minecraft_version(state.launcher_path.clone(), version, self.deep_verify),
+                                        |v| { Message::DownloaderMessage(DownloaderMessage::DownloadFinished(v)) });
+            }
+            DownloaderMessage::DownloadFinished(result) => {
+                self.progress = None;
+                match result {
+                    Ok(id) => self.status = Some(format!("Version {0} finished downloading.", id)),
+                    Err(e) => self.error = Some(e),
+                }
+                state.refresh_installed();
+                return self.load_disk_usage(state);
+            }
+            DownloaderMessage::VerifyPressed(repair) => {
+                let version = self.selected_version.version(&state.versions);
+                let minecraft_path = state.launcher_path.clone();
+                let deep_verify = self.deep_verify;
+                self.start(format!("{0} {1}...", if repair { "Repairing" } else { "Verifying" }, version.id));
+                return Command::perform(async move {
+                    if repair {
+                        return repair_version(minecraft_path, version, None, deep_verify).await.map(|(before, after)| (before, Some(after)));
+                    }
+                    return verify_version(minecraft_path, version, None, deep_verify).await.map(|report| (report, None));
+                }, |r| { Message::DownloaderMessage(DownloaderMessage::VerifyFinished(r)) });
+            }
+            DownloaderMessage::VerifyFinished(result) => {
+                self.progress = None;
+                self.verify_result = Some(result);
+                state.refresh_installed();
+                return self.load_disk_usage(state);
+            }
+            DownloaderMessage::UninstallPressed => {
+                let id = self.selected_version.version(&state.versions).id;
+                let minecraft_path = state.launcher_path.clone();
+                let remove_orphaned = self.remove_orphaned;
+                self.start(format!("Uninstalling {0}...", id));
+                return Command::perform(async move {
+                    let result = uninstall_version(&minecraft_path, &id, remove_orphaned);
+                    (id, result)
+                }, |(id, result)| { Message::DownloaderMessage(DownloaderMessage::UninstallFinished(id, result)) });
+            }
+            DownloaderMessage::UninstallFinished(id, result) => {
+                self.progress = None;
+                match result {
+                    Ok(report) => {
+                        self.status = Some(format!("Uninstalled {0}, freeing {1}", id, format_size(report.reclaimed)));
+                        if !report.warnings.is_empty() {
+                            self.error = Some(report.warnings.join("\n"));
                         }
                     },
-                    VersionSelection::Version(v) => { version = &v; },
-                };
+                    Err(e) => self.error = Some(e),
+                }
+                state.refresh_installed();
+            }
+            DownloaderMessage::ReinstallPressed => {
+                let version = self.selected_version.version(&state.versions);
+                self.start(format!("Reinstalling {0}...", version.id));
+                return Command::perform(reinstall_minecraft_version(state.launcher_path.clone(), version),
+                                        |r| { Message::DownloaderMessage(DownloaderMessage::ReinstallFinished(r)) });
+            }
+            DownloaderMessage::ReinstallFinished(result) => {
+                self.progress = None;
+                match result {
+                    Ok(id) => self.status = Some(format!("Version {0} reinstalled.", id)),
+                    Err(e) => self.error = Some(e),
+                }
+                state.refresh_installed();
+                return self.load_disk_usage(state);
+            }
+            DownloaderMessage::DiskUsageLoaded(id, version_size, freed) => {
+                self.disk_usage = Some((id, version_size, freed));
+            }
+        }
+        return Command::none();
+    }
+}
 
-                return Command::perform(download_minecraft_version(state.launcher_path.clone(), version.clone()),
-                                        |v| { Message::DownloaderMessage(DownloaderMessage::DownloadFinished(v)) });
+#[derive(Debug, Clone)]
+enum LogsMessage {
+    Tick,
+    SearchChanged(String),
+    AutoScrollToggled(bool),
+    DeobfuscateToggled(bool),
+    MappingsLoaded(String, Result<Arc<Mappings>, String>),
+    CopyPressed,
+}
+
+// A log record as it's shown, worked out once rather than on every render
+struct LogLine {
+    text: String,
+    // For searching without lowercasing every line again
+    lowercase: String,
+    color: Color,
+}
+
+struct Logs {
+    search: String,
+    auto_scroll: bool,
+    deobfuscate: bool,
+    // Every record of the session the lines were made from, and the indexes of the ones matching the search
+    lines_log: Option<Arc<SessionLog>>,
+    lines: Vec<LogLine>,
+    matching: Vec<usize>,
+    // Mappings for the version they were loaded for
+    mappings: Option<(String, Arc<Mappings>)>,
+    mappings_error: Option<String>,
+    // Version the mappings are being loaded for, so that every tick doesn't start loading them again
+    mappings_pending: Option<String>,
+
+    search_input_state: text_input::State,
+    copy_button_state: button::State,
+    log_scroll_state: scrollable::State,
+}
+
+impl Logs {
+    // Only the most recent lines are shown, since every line is its own widget
+    const MAX_SHOWN_RECORDS: usize = 1000;
+
+    fn new() -> Self {
+        Logs {
+            search: String::from(""),
+            auto_scroll: true,
+            deobfuscate: false,
+            lines_log: None,
+            lines: Vec::new(),
+            matching: Vec::new(),
+            mappings: None,
+            mappings_error: None,
+            mappings_pending: None,
+
+            search_input_state: text_input::State::default(),
+            copy_button_state: button::State::default(),
+            log_scroll_state: scrollable::State::default(),
+        }
+    }
+
+    fn record_color(record: &LogRecord) -> Color {
+        if record.source == LogSource::Launcher {
+            return Color::from_rgb(0.1, 0.3, 0.8);
+        }
+        if record.source == LogSource::Hook && record.level == LogLevel::Info {
+            return Color::from_rgb(0.4, 0.1, 0.6);
+        }
+        match record.level {
+            LogLevel::Trace | LogLevel::Debug => Color::from_rgb(0.5, 0.5, 0.5),
+            LogLevel::Info | LogLevel::Unknown => Color::BLACK,
+            LogLevel::Warn => Color::from_rgb(0.8, 0.5, 0.0),
+            LogLevel::Error | LogLevel::Fatal => Color::from_rgb(0.8, 0.0, 0.0),
+        }
+    }
+
+    // Adds the lines for records logged since the last refresh, starting over for a new session
+    // Returns whether there were any
+    fn refresh_lines(&mut self, state: &ApplicationState) -> bool {
+        if !self.lines_log.as_ref().is_some_and(|log| Arc::ptr_eq(log, &state.session_log)) {
+            self.lines_log = Some(state.session_log.clone());
+            self.lines.clear();
+            self.matching.clear();
+        }
+        let records = state.session_log.records_since(self.lines.len());
+        let search = self.search.to_lowercase();
+        for record in records.iter() {
+            let text = self.record_text(state, record);
+            let lowercase = text.to_lowercase();
+            if lowercase.contains(&search) {
+                self.matching.push(self.lines.len());
             }
-            DownloaderMessage::DownloadFinished(id) => {
-                self.last_downloaded_id = Some(id)
+            self.lines.push(LogLine {
+                text: text,
+                lowercase: lowercase,
+                color: Logs::record_color(record),
+            });
+        }
+        return !records.is_empty();
+    }
+
+    // For when the text of every line changes, ie when turning deobfuscation on or off
+    fn rebuild_lines(&mut self, state: &ApplicationState) {
+        self.lines_log = None;
+        self.refresh_lines(state);
+    }
+
+    fn filter_lines(&mut self) {
+        let search = self.search.to_lowercase();
+        self.matching = self.lines.iter().enumerate().filter(|(_, line)| line.lowercase.contains(&search)).map(|(i, _)| i).collect();
+    }
+
+    // The text shown for a record, with names remapped if deobfuscating
+    fn record_text(&self, state: &ApplicationState, record: &LogRecord) -> String {
+        if self.deobfuscate {
+            if let Some((version, mappings)) = &self.mappings {
+                if state.session_log.version.as_ref() == Some(version) {
+                    return mappings.remap(&record.to_string());
+                }
             }
         }
+        return record.to_string();
+    }
+
+    fn load_mappings(&mut self, state: &ApplicationState) -> Command<Message> {
+        let version = match &state.session_log.version {
+            Some(version) => version.clone(),
+            None => return Command::none(),
+        };
+        if self.mappings.as_ref().is_some_and(|(v, _)| *v == version) || self.mappings_pending.as_ref() == Some(&version) {
+            return Command::none();
+        }
+        self.mappings_pending = Some(version.clone());
+        return Command::perform(get_client_mappings(state.launcher_path.clone(), version.clone()),
+                                move |r| { Message::LogsMessage(LogsMessage::MappingsLoaded(version.clone(), r)) });
+    }
+
+    fn view(&mut self, state: &ApplicationState) -> Element<'_, Message> {
+        let shown = &self.matching[self.matching.len().saturating_sub(Logs::MAX_SHOWN_RECORDS)..];
+        let lines: Vec<(String, Color)> = shown.iter().map(|i| (self.lines[*i].text.clone(), self.lines[*i].color)).collect();
+
+        let mut log_view = Scrollable::new(&mut self.log_scroll_state)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(5);
+        if lines.is_empty() {
+            log_view = log_view.push(Text::new("No output yet.").size(14));
+        }
+        for (text, color) in lines {
+            log_view = log_view.push(Text::new(text).size(14).color(color));
+        }
+
+        let mut content = Column::new()
+            .align_items(Alignment::Center)
+            .push(
+                Row::new()
+                .align_items(Alignment::Center)
+                .push(
+                    TextInput::new(&mut self.search_input_state, "Search...", &self.search,
+                                   move |s| { Message::LogsMessage(LogsMessage::SearchChanged(s)) })
+                    .padding(5)
+                    .width(Length::Fill)
+                ).push(Space::with_width(Length::Units(10)))
+                .push(
+                    Button::new(&mut self.copy_button_state, Text::new("Copy"))
+                        .on_press(Message::LogsMessage(LogsMessage::CopyPressed))
+                )
+            ).push(Space::with_height(Length::Units(5)))
+            .push(
+                Row::new()
+                .push(
+                    Checkbox::new(self.auto_scroll, "Auto-scroll",
+                                  move |b| { Message::LogsMessage(LogsMessage::AutoScrollToggled(b)) })
+                ).push(Space::with_width(Length::Units(20)))
+                .push(
+                    Checkbox::new(self.deobfuscate, "Deobfuscate",
+                                  move |b| { Message::LogsMessage(LogsMessage::DeobfuscateToggled(b)) })
+                )
+            ).push(Space::with_height(Length::Units(5)))
+            .push(log_view);
+
+        if let Some(e) = &self.mappings_error {
+            content = content.push(Text::new(e).size(14));
+        }
+        if let Some(path) = state.session_log.file_path() {
+            content = content.push(Text::new(format!("Saved to {0}", path)).size(14));
+        }
+        content = content.padding(10);
+
+        return content.into();
+    }
+
+    fn update(&mut self, state: &mut ApplicationState, message: LogsMessage) -> Command<Message> {
+        match message {
+            LogsMessage::Tick => {
+                if self.refresh_lines(state) && self.auto_scroll {
+                    self.log_scroll_state.snap_to(1.0);
+                }
+
+                // A new session might be for a different version
+                if self.deobfuscate {
+                    return self.load_mappings(state);
+                }
+            },
+            LogsMessage::SearchChanged(search) => {
+                self.search = search;
+                self.filter_lines();
+            },
+            LogsMessage::AutoScrollToggled(auto_scroll) => {
+                self.auto_scroll = auto_scroll;
+                if self.auto_scroll {
+                    self.log_scroll_state.snap_to(1.0);
+                }
+            },
+            LogsMessage::DeobfuscateToggled(deobfuscate) => {
+                self.deobfuscate = deobfuscate;
+                self.mappings_error = None;
+                self.rebuild_lines(state);
+                if self.deobfuscate {
+                    return self.load_mappings(state);
+                }
+            },
+            LogsMessage::MappingsLoaded(version, result) => {
+                if self.mappings_pending.as_ref() == Some(&version) {
+                    self.mappings_pending = None;
+                }
+                match result {
+                    Ok(mappings) => self.mappings = Some((version, mappings)),
+                    Err(e) => {
+                        self.mappings_error = Some(e);
+                        self.deobfuscate = false;
+                    },
+                }
+                self.rebuild_lines(state);
+            },
+            LogsMessage::CopyPressed => {
+                // Copies everything matching the search, not just what's shown
+                let text: Vec<&str> = self.matching.iter().map(|i| self.lines[*i].text.as_str()).collect();
+                return clipboard::write(text.join("\n"));
+            },
+        }
         return Command::none();
     }
 }
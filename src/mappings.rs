@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    // Matches qualified names, optionally followed by the location in a stack frame, ie "ezk.a(SourceFile:123)"
+    static ref NAME_REGEX: Regex = Regex::new(r"[A-Za-z_$][\w$]*(?:\.[A-Za-z_$<][\w$<>]*)*(\([^)]*?(?::(\d+))?\))?").unwrap();
+    // Optional line numbers at the start of a method mapping, ie "12:34:void tick() -> a"
+    static ref LINE_RANGE_REGEX: Regex = Regex::new(r"^(\d+):(\d+):").unwrap();
+}
+
+#[derive(Debug)]
+struct MemberMapping {
+    name: String,
+    // Range of obfuscated line numbers the method covers, used to pick between overloads
+    lines: Option<(u32, u32)>,
+}
+
+// Lookup from obfuscated names to readable names, parsed from a ProGuard mapping file
+#[derive(Debug)]
+pub struct Mappings {
+    classes: HashMap<String, String>,
+    // Keyed by "{obfuscated class}.{obfuscated member}"
+    members: HashMap<String, Vec<MemberMapping>>,
+}
+
+impl Mappings {
+    pub fn parse(mappings: &str) -> Mappings {
+        let mut classes = HashMap::new();
+        let mut members: HashMap<String, Vec<MemberMapping>> = HashMap::new();
+        let mut current_class = String::new();
+
+        for line in mappings.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let (readable, obfuscated) = match line.trim().split_once(" -> ") {
+                Some(names) => names,
+                None => continue,
+            };
+
+            // Class lines aren't indented, ie "net.minecraft.client.Minecraft -> ezk:"
+            if !line.starts_with(' ') {
+                current_class = obfuscated.trim_end_matches(':').to_string();
+                classes.insert(current_class.clone(), readable.to_string());
+                continue;
+            }
+
+            // Member lines are "[start:end:]type name[(args)[:start:end]] -> obfuscated"
+            let lines = LINE_RANGE_REGEX.captures(readable).map(|c| (c[1].parse().unwrap(), c[2].parse().unwrap()));
+            let declaration = LINE_RANGE_REGEX.replace(readable, "");
+            let declaration = declaration.split('(').next().unwrap();
+            let name = declaration.rsplit(' ').next().unwrap();
+            members.entry(format!("{0}.{1}", current_class, obfuscated))
+                .or_default()
+                .push(MemberMapping {
+                    name: name.to_string(),
+                    lines: lines,
+                });
+        }
+
+        Mappings {
+            classes: classes,
+            members: members,
+        }
+    }
+
+    pub fn class(&self, obfuscated: &str) -> Option<&str> {
+        return self.classes.get(obfuscated).map(|c| c.as_str());
+    }
+
+    pub fn member(&self, obfuscated_class: &str, obfuscated: &str, line: Option<u32>) -> Option<String> {
+        let candidates = self.members.get(&format!("{0}.{1}", obfuscated_class, obfuscated))?;
+
+        // Prefer the method whose line numbers contain the line from the stack frame
+        if let Some(line) = line {
+            if let Some(m) = candidates.iter().find(|m| m.lines.is_some_and(|(start, end)| start <= line && line <= end)) {
+                return Some(m.name.clone());
+            }
+        }
+
+        // Otherwise list every distinct name, since overloads can share an obfuscated name
+        let mut names: Vec<&str> = Vec::new();
+        for m in candidates.iter() {
+            if !names.contains(&m.name.as_str()) {
+                names.push(&m.name);
+            }
+        }
+        return Some(names.join("|"));
+    }
+
+    // Replaces obfuscated class and member names in a crash report, log or stack trace
+    // Only qualified names are touched, since obfuscated classes can be called things like "a"
+    pub fn remap(&self, text: &str) -> String {
+        return NAME_REGEX.replace_all(text, |captures: &Captures| {
+            let full_match = &captures[0];
+            let location = captures.get(1).map_or("", |l| l.as_str());
+            let name = &full_match[..full_match.len() - location.len()];
+            let line = captures.get(2).and_then(|l| l.as_str().parse().ok());
+
+            if !name.contains('.') && !name.contains('$') {
+                return full_match.to_string();
+            }
+            if let Some(class) = self.class(name) {
+                return format!("{0}{1}", class, location);
+            }
+            if let Some((obfuscated_class, obfuscated_member)) = name.rsplit_once('.') {
+                if let Some(class) = self.class(obfuscated_class) {
+                    let member = self.member(obfuscated_class, obfuscated_member, line).unwrap_or_else(|| obfuscated_member.to_string());
+                    return format!("{0}.{1}{2}", class, member, location);
+                }
+            }
+            return full_match.to_string();
+        }).into_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPPINGS: &str = "# compiler: R8
+net.minecraft.client.Minecraft -> ezk:
+    java.lang.String version -> a
+    1:10:void tick() -> b
+    11:20:void tick(boolean) -> b
+    21:30:void render() -> b
+net.minecraft.world.level.Level -> cmm:
+    void save() -> a
+";
+
+    #[test]
+    fn parse_reads_classes_and_members() {
+        let mappings = Mappings::parse(MAPPINGS);
+        assert_eq!(mappings.class("ezk"), Some("net.minecraft.client.Minecraft"));
+        assert_eq!(mappings.class("cmm"), Some("net.minecraft.world.level.Level"));
+        assert_eq!(mappings.class("a"), None);
+        assert_eq!(mappings.member("ezk", "a", None), Some(String::from("version")));
+        assert_eq!(mappings.member("cmm", "a", None), Some(String::from("save")));
+        assert_eq!(mappings.member("cmm", "b", None), None);
+    }
+
+    #[test]
+    fn member_uses_line_numbers_for_overloads() {
+        let mappings = Mappings::parse(MAPPINGS);
+        assert_eq!(mappings.member("ezk", "b", Some(5)), Some(String::from("tick")));
+        assert_eq!(mappings.member("ezk", "b", Some(25)), Some(String::from("render")));
+        assert_eq!(mappings.member("ezk", "b", Some(99)), Some(String::from("tick|render")));
+        assert_eq!(mappings.member("ezk", "b", None), Some(String::from("tick|render")));
+    }
+
+    #[test]
+    fn remap_replaces_qualified_names() {
+        let mappings = Mappings::parse(MAPPINGS);
+        assert_eq!(mappings.remap("at ezk.b(SourceFile:25)"), "at net.minecraft.client.Minecraft.render(SourceFile:25)");
+        assert_eq!(mappings.remap("Saving cmm.a in cmm"), "Saving net.minecraft.world.level.Level.save in cmm");
+        assert_eq!(mappings.remap("java.lang.NullPointerException: ezk"), "java.lang.NullPointerException: ezk");
+        assert_eq!(mappings.remap("at java.lang.Thread.run(Thread.java:833)"), "at java.lang.Thread.run(Thread.java:833)");
+    }
+}
@@ -6,184 +6,132 @@ use walkdir::WalkDir;
 use std::path::Path;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::collections::BTreeMap;
-use std::process::ExitStatus;
-use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use serde_json::Value;
 use async_std::process::Command;
+use async_std::io::BufReader;
+use async_std::io::prelude::BufReadExt;
+use async_std::task;
 use bytes::Buf;
 use futures::stream::{self, StreamExt};
 
-use crate::env::Environment;
+use crate::config::LauncherConfig;
+use crate::crash::{CrashReport, find_crash_report};
+use crate::hash_cache::HashCache;
+use crate::env::{EnvLayer, Environment, LayeredEnvironment, ResolveError};
+use crate::hooks::run_hook;
+use crate::instance::Instance;
+use crate::launch_mode::LaunchModes;
+use crate::link::{convert_to_hardlink, place_file};
+use crate::log::{LogParser, LogRecord, LogSource, SessionLog};
+use crate::mappings::Mappings;
+use crate::net;
+use crate::pool::map_parallel;
+use crate::process::{ProcessRegistry, RunningGame};
+use crate::proxy::PASSWORD_VARIABLE;
+use crate::spec::*;
 use crate::util::*;
 
-// TODO: Move all these types to their own file where it won't clutter everything
-// Types for version list JSON
-#[derive(Deserialize)]
-pub struct MinecraftLatestVersions {
-    pub release: String,
-    pub snapshot: String,
-}
-
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct MinecraftVersion {
-    pub id: String,
-    #[serde(rename="type")]
-    pub version_type: String,
-    url: String,
-    time: String,
-    #[serde(rename="releaseTime")]
-    release_time: String,
-}
-
-#[derive(Deserialize)]
-pub struct MinecraftVersionList {
-    pub latest: MinecraftLatestVersions,
-    pub versions: Vec<MinecraftVersion>,
-}
-
-// Types for version spec JSON
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum SingleOrVec<T> {
-    Single(T),
-    Vector(Vec<T>),
-}
-
-#[derive(Deserialize)]
-struct DynamicArgument {
-    rules: Vec<Rule>,
-    value: SingleOrVec<String>,
-}
-
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum Argument {
-    Static(String),
-    Dynamic(DynamicArgument),
-}
-
-#[derive(Deserialize)]
-struct VersionArguments {
-    game: Vec<Argument>,
-    jvm: Vec<Argument>,
-}
-
-#[derive(Deserialize)]
-struct VersionAssets {
-    id: String,
-    sha1: String,
-    size: u64,
-    #[serde(rename="totalSize")]
-    total_size: u64,
-    url: String
-}
-
-#[derive(Deserialize)]
-struct Download {
-    path: Option<String>,
-    sha1: String,
-    size: u64,
-    url: String,
-}
-
-#[derive(Deserialize)]
-struct VersionDownloads {
-    client: Download,
-    // Server doesn't exist for versions before 1.2.5
-    server: Option<Download>,
-    // Deobfuscation mappings don't exist for versions before 1.14.4
-    client_mappings: Option<Download>,
-    server_mappings: Option<Download>,
-}
-
-#[derive(Deserialize)]
-struct LibraryDownloads {
-    // Apparently in older versions some libraries might not have an artifact
-    artifact: Option<Download>,
-    // This doesn't have a fully specified layout because a classifier can be called anything
-    classifiers: Option<BTreeMap<String, Download>>,
-}
-
-#[derive(Deserialize)]
-struct LibraryNatives {
-    linux: Option<String>,
-    osx: Option<String>,
-    windows: Option<String>,
+// Gets the list of versions, saving it to "{launcher}/version_manifest_v2.json"
+// The saved list is used when it can't be downloaded, ie while offline or when the proxy can't be reached
+pub async fn get_version_list(minecraft_path: &str) -> Result<MinecraftVersionList, String> {
+    let list_path = format!("{0}/version_manifest_v2.json", minecraft_path);
+    let downloaded = match net::get("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json").await {
+        Ok(response) => response.text().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    let downloaded = downloaded.and_then(|list_json| {
+        let version_list: MinecraftVersionList = serde_json::from_str(&list_json).map_err(|e| e.to_string())?;
+        return Ok((list_json, version_list));
+    });
+    match downloaded {
+        Ok((list_json, version_list)) => {
+            if let Err(e) = fs::create_dir_all(minecraft_path).map_err(|e| e.to_string()).and_then(|_| write_atomic(Path::new(&list_path), list_json.as_bytes())) {
+                println!("Failed to save the version list: {0}", e);
+            }
+            return Ok(version_list);
+        },
+        Err(e) => {
+            println!("Failed to download the version list, using the saved one: {0}", e);
+            let list_json = fs::read_to_string(&list_path).map_err(|_| format!("Failed to download the version list and there isn't a saved one: {0}", e))?;
+            return serde_json::from_str(&list_json).map_err(|e| format!("Failed to parse {0}: {1}", list_path, e));
+        },
+    }
 }
 
-#[derive(Deserialize)]
-struct LibraryExtractOptions {
-    exclude: Vec<String>,
+// How a launched game ended, for showing in the launcher tab
+#[derive(Debug, Clone)]
+pub struct GameExit {
+    pub version: String,
+    pub status: ExitStatus,
+    pub crash: Option<CrashReport>,
+    pub log_path: Option<String>,
+    // Recordings and logs written by the launch modes
+    pub output_files: Vec<String>,
 }
 
-#[derive(Deserialize)]
-struct RuleOS {
-    name: Option<String>,
-    version: Option<String>,
-    arch: Option<String>,
+// Everything needed to start the game once it's been prepared
+#[derive(Serialize, Debug, Clone)]
+pub struct LaunchCommand {
+    // Java (or the wrapper command) followed by its arguments
+    pub command_line: Vec<String>,
+    pub working_directory: String,
+    pub env_vars: BTreeMap<String, String>,
+    pub unset_env_vars: Vec<String>,
+    // Files the launch modes will write while the game runs
+    pub output_files: Vec<String>,
+    // Parts of the version spec that were ignored
+    pub warnings: Vec<String>,
 }
 
-#[derive(Deserialize)]
-struct Rule {
-    action: String,
-    os: Option<RuleOS>,
-    features: Option<BTreeMap<String, bool>>,
-}
+impl LaunchCommand {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).unwrap();
+    }
 
-#[derive(Deserialize)]
-struct Library {
-    downloads: LibraryDownloads,
-    name: String,
-    natives: Option<LibraryNatives>,
-    extract: Option<LibraryExtractOptions>,
-    rules: Option<Vec<Rule>>,
-}
+    // A standalone shell script that starts the game the same way the launcher would
+    pub fn to_shell_script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\n# Generated by Minelaunch\n");
+        for name in self.unset_env_vars.iter() {
+            script += &format!("unset {0}\n", name);
+        }
+        for (name, value) in self.env_vars.iter() {
+            script += &format!("export {0}={1}\n", name, shell_quote(value));
+        }
+        script += &format!("cd {0} || exit 1\n", shell_quote(&self.working_directory));
+        let command_line: Vec<String> = self.command_line.iter().map(|a| shell_quote(a)).collect();
+        script += &format!("exec {0}\n", command_line.join(" \\\n    "));
+        return script;
+    }
 
-#[derive(Deserialize)]
-struct JavaVersion {
-    component: String,
-    #[serde(rename="majorVersion")]
-    major_version: u8,
+    // The same for Windows, as a batch file
+    pub fn to_batch_script(&self) -> String {
+        let mut script = String::from("@echo off\r\nrem Generated by Minelaunch\r\n");
+        for name in self.unset_env_vars.iter() {
+            script += &format!("set {0}=\r\n", name);
+        }
+        for (name, value) in self.env_vars.iter() {
+            script += &format!("set \"{0}={1}\"\r\n", name, value.replace('%', "%%"));
+        }
+        script += &format!("cd /d {0} || exit /b 1\r\n", batch_quote(&self.working_directory));
+        let command_line: Vec<String> = self.command_line.iter().map(|a| batch_quote(a)).collect();
+        script += &format!("{0}\r\n", command_line.join(" ^\r\n    "));
+        return script;
+    }
 }
 
-// TODO: Properly fill out the entire spec struct
-#[derive(Deserialize)]
-struct VersionSpec {
-    arguments: Option<VersionArguments>,
-    #[serde(rename="assetIndex")]
-    asset_index: VersionAssets,
-    assets: String,
-    downloads: VersionDownloads,
-    id: String,
-    #[serde(rename="javaVersion")]
-    java_version: Option<JavaVersion>,
-    libraries: Vec<Library>,
-    #[serde(rename="mainClass")]
-    main_class: String,
-    #[serde(rename="minecraftArguments")]
-    minecraft_arguments: Option<String>,
-    #[serde(rename="minimumLauncherVersion")]
-    minimum_launcher_version: u8,
-    #[serde(rename="type")]
-    version_type: String,
-}
-
-#[derive(Deserialize)]
-struct AssetObject {
-    hash: String,
-    size: u64,
-}
-
-#[derive(Deserialize)]
-struct AssetIndex {
-    objects: BTreeMap<String, AssetObject>,
-    #[serde(rename="virtual")]
-    virtual_assets: Option<bool>,
-    map_to_resources: Option<bool>,
+// The result of a dry launch, where the game is prepared but not started
+#[derive(Debug, Clone)]
+pub struct DryLaunch {
+    pub script_path: String,
+    pub command: LaunchCommand,
 }
 
-
-async fn download_java(save_path: &str, version: u8) {
+async fn download_java(save_path: &str, version: u8) -> Result<(), String> {
     // Download Java runtime
     // Need to download JRE for Java 8, JDK for Java 16+ and then jlink
     println!("Downloading Java {0} for {1}-{2}", version, get_os(), get_arch());
@@ -194,28 +142,34 @@ async fn download_java(save_path: &str, version: u8) {
     else {
         java_url = format!("https://api.adoptium.net/v3/binary/latest/{0}/ga/{1}/{2}/jdk/hotspot/normal/eclipse", version, get_os_java(), get_arch_java());
     }
-    let response = reqwest::get(&java_url).await.unwrap();
+    let response = net::get(&java_url).await.map_err(|e| format!("Failed to download Java {0}: {1}", version, e))?;
+    let java_bytes = response.bytes().await.map_err(|e| format!("Failed to download Java {0}: {1}", version, e))?;
 
     // Extract Java runtime to tempdir
     println!("Extracting Java");
-    let extract_dir = tempdir().unwrap();
+    let extract_error = |e: &dyn std::fmt::Display| format!("Failed to extract Java {0}: {1}", version, e);
+    let extract_dir = tempdir().map_err(|e| extract_error(&e))?;
     if get_os() == "windows" {
-        let mut temp_file = tempfile().unwrap();
-        temp_file.write_all(&response.bytes().await.unwrap()).unwrap();
-        let mut archive = ZipArchive::new(temp_file).unwrap();
-        archive.extract(extract_dir.path()).unwrap();
+        let mut temp_file = tempfile().map_err(|e| extract_error(&e))?;
+        temp_file.write_all(&java_bytes).map_err(|e| extract_error(&e))?;
+        let mut archive = ZipArchive::new(temp_file).map_err(|e| extract_error(&e))?;
+        archive.extract(extract_dir.path()).map_err(|e| extract_error(&e))?;
     }
     else {
-        let mut archive = Archive::new(GzDecoder::new(response.bytes().await.unwrap().reader()));
-        archive.unpack(extract_dir.path()).unwrap();
+        let mut archive = Archive::new(GzDecoder::new(java_bytes.reader()));
+        archive.unpack(extract_dir.path()).map_err(|e| extract_error(&e))?;
     }
-    let version_folder = fs::read_dir(&extract_dir).unwrap().next().unwrap().unwrap().path();
+    let version_folder = match fs::read_dir(&extract_dir).map_err(|e| extract_error(&e))?.next() {
+        Some(entry) => entry.map_err(|e| extract_error(&e))?.path(),
+        None => return Err(format!("Failed to extract Java {0}: the download was empty", version)),
+    };
 
     // Move/Make JRE to "{save-path}/runtime/java{version}-{os}-{arch}/"
     let runtime_dir = format!("{0}/runtime/java{1}-{2}-{3}/", save_path, version, get_os(), get_arch());
+    let install_error = |e: &dyn std::fmt::Display| format!("Failed to install Java {0} to {1}: {2}", version, runtime_dir, e);
     // Create runtime folder if it doesn't exist
     if !Path::new(&format!("{0}/runtime/", save_path)).exists() {
-        fs::create_dir_all(&format!("{0}/runtime/", save_path)).unwrap();
+        fs::create_dir_all(format!("{0}/runtime/", save_path)).map_err(|e| install_error(&e))?;
     }
     // Need to move JRE for Java 8
     if version == 8 {
@@ -223,25 +177,25 @@ async fn download_java(save_path: &str, version: u8) {
         if get_os() == "windows" {
             // fs::rename doesn't work across drive letters, so I manually copy every file to move the folder
             // Don't need to worry about deleting the files because they're in a tempdir that gets automatically removed
-            fs::create_dir(&runtime_dir).unwrap();
+            fs::create_dir(&runtime_dir).map_err(|e| install_error(&e))?;
             for entry in WalkDir::new(&version_folder).min_depth(1) {
-                let entry = entry.unwrap();
+                let entry = entry.map_err(|e| install_error(&e))?;
                 let unprefixed_entry = entry.path().strip_prefix(&version_folder).unwrap();
                 if entry.path().is_dir() {
-                    fs::create_dir(Path::new(&runtime_dir).join(unprefixed_entry)).unwrap();
+                    fs::create_dir(Path::new(&runtime_dir).join(unprefixed_entry)).map_err(|e| install_error(&e))?;
                 }
                 else if entry.path().is_file() {
-                    fs::copy(entry.path(), Path::new(&runtime_dir).join(unprefixed_entry)).unwrap();
+                    fs::copy(entry.path(), Path::new(&runtime_dir).join(unprefixed_entry)).map_err(|e| install_error(&e))?;
                 }
             }
         }
         else if get_os() == "macos" {
             // Mac OS X has a weird JRE file structure compared to Windows/Linux
-            fs::rename(version_folder.join("Contents/Home"), &runtime_dir).unwrap();
-            fs::rename(version_folder.join("Contents/MacOS/libjli.dylib"), Path::new(&runtime_dir).join("bin/libjli.dylib")).unwrap();
+            fs::rename(version_folder.join("Contents/Home"), &runtime_dir).map_err(|e| install_error(&e))?;
+            fs::rename(version_folder.join("Contents/MacOS/libjli.dylib"), Path::new(&runtime_dir).join("bin/libjli.dylib")).map_err(|e| install_error(&e))?;
         }
         else if get_os() == "linux" {
-            fs::rename(version_folder, &runtime_dir).unwrap();
+            fs::rename(version_folder, &runtime_dir).map_err(|e| install_error(&e))?;
         }
     }
     // Need to jlink the JDK to create the JRE for Java 16+
@@ -258,30 +212,174 @@ async fn download_java(save_path: &str, version: u8) {
         let mut jlink_process = Command::new(jlink_path);
         jlink_process.args(vec!["--add-modules", "ALL-MODULE-PATH", "--output", &runtime_dir,
                                 "--strip-debug", "--no-man-pages", "--no-header-files", "--compress=2"]);
-        let status = jlink_process.status().await.unwrap();
+        let status = jlink_process.status().await.map_err(|e| install_error(&format!("jlink couldn't be run: {0}", e)))?;
         println!("jlink exited with {0}", status);
+        if !status.success() {
+            return Err(install_error(&format!("jlink exited with {0}", status)));
+        }
     }
 
     println!("Java extracted to runtime/java{0}-{1}-{2}/", version, get_os(), get_arch());
+    return Ok(());
 }
 
-pub async fn launch_minecraft_version(minecraft_path: String, version: MinecraftVersion, env: Box<Environment>) -> ExitStatus {
-    let mut env = *env;
+pub async fn launch_minecraft_version(minecraft_path: String, version: MinecraftVersion, instance: Instance, modes: LaunchModes, env: Box<LayeredEnvironment>, log: Arc<SessionLog>, processes: Arc<ProcessRegistry>) -> Result<GameExit, String> {
+    // Registered before preparing, so the instance already counts as running and the launch can be stopped
+    let game = processes.register(&instance.name, &version.id);
+    let result = run_minecraft_version(minecraft_path, version, instance, modes, *env, log, &game).await;
+    processes.unregister(game.id);
+    return result;
+}
+
+async fn run_minecraft_version(minecraft_path: String, version: MinecraftVersion, mut instance: Instance, modes: LaunchModes, mut env: LayeredEnvironment, log: Arc<SessionLog>, game: &RunningGame) -> Result<GameExit, String> {
+    env.set_layer(EnvLayer::Instance, instance.load_environment()?);
+
+    // Create the instance's game directory if it doesn't exist
+    instance.settings.last_version = Some(version.id.clone());
+    instance.save();
+    env.set("game_directory", &instance.path);
+    env.set("instance_name", &instance.name);
+
+    // Save the output to a log file for this session
+    let session_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let log_path = format!("{0}/logs/session-{1}.log", instance.path, session_time);
+    fs::create_dir_all(format!("{0}/logs/", instance.path)).map_err(|e| format!("Failed to create the logs folder of {0}: {1}", instance.name, e))?;
+    log.create_file(&log_path)?;
+
+    // Extract natives to a temporary directory that gets cleaned up when the game exits
+    let natives_dir = tempdir().unwrap();
+    let natives_path = natives_dir.path().to_str().unwrap();
+    let launch_command = prepare_launch(&minecraft_path, &version, &instance, &modes, session_time, &mut env, natives_path).await?;
+
+    for warning in launch_command.warnings.iter() {
+        println!("Warning: {0}", warning);
+        log.push(LogRecord::launcher(&format!("Warning: {0}", warning)));
+    }
+
+    // The pre-launch hook can stop the launch by failing
+    if let Some(hook) = &instance.settings.pre_launch_hook {
+        let status = run_hook("pre-launch", hook, &instance.path, &env.flatten(), &log).await?;
+        if !status.success() {
+            return Err(format!("The pre-launch hook exited with {0}, not launching", status));
+        }
+    }
+
+    if game.is_killed() {
+        return Err(String::from("The launch was stopped"));
+    }
+
+    // Run Minecraft
+    println!("Launching Minecraft {0} in instance {1}", version.id, instance.name);
+    log.push(LogRecord::launcher(&format!("Launching Minecraft {0} in instance {1}", version.id, instance.name)));
+    let command_line = &launch_command.command_line;
+    let mut java_process = Command::new(&command_line[0]);
+    java_process.args(&command_line[1..]);
+    java_process.current_dir(&launch_command.working_directory);
+    for name in launch_command.unset_env_vars.iter() {
+        java_process.env_remove(name);
+    }
+    for (name, value) in launch_command.env_vars.iter() {
+        java_process.env(name, value);
+    }
+    java_process.stdout(Stdio::piped());
+    java_process.stderr(Stdio::piped());
+    let mut child = match java_process.spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(format!("Failed to start {0}: {1}", command_line[0], e)),
+    };
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let pid = game.attach(child);
+    log.push(LogRecord::launcher(&format!("Started process {0}", pid)));
+
+    // Read stdout and stderr together until both are closed
+    let stdout_lines = BufReader::new(stdout).lines().map(|l| (LogSource::Stdout, l));
+    let stderr_lines = BufReader::new(stderr).lines().map(|l| (LogSource::Stderr, l));
+    let mut output_lines = stream::select(stdout_lines, stderr_lines);
+    let mut parser = LogParser::new();
+    while let Some((source, line)) = output_lines.next().await {
+        // Skip lines that aren't valid UTF-8
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+        let record = match source {
+            LogSource::Stdout => parser.parse_line(&line),
+            _ => Some(LogRecord::plain(source, &line)),
+        };
+        if let Some(record) = record {
+            log.push(record);
+        }
+    }
+
+    // The output closing usually means the game exited, but wait for it to be sure
+    let status = loop {
+        if let Some(status) = game.try_status() {
+            break status;
+        }
+        task::sleep(Duration::from_millis(100)).await;
+    };
+    println!("Minecraft exited with {0}", status);
+    log.push(LogRecord::launcher(&format!("Minecraft exited with {0}", status)));
+
+    // Look for anything the game or the JVM wrote about a crash
+    let crash = find_crash_report(&instance.path, pid, game.start_time().unwrap());
+    if let Some(crash) = &crash {
+        println!("Minecraft crashed, see {0}", crash.path);
+        log.push(LogRecord::launcher(&format!("Minecraft crashed, see {0}", crash.path)));
+    }
+
+    // Only list the files that actually got written, ie the recording is missing if the JVM crashed
+    let output_files: Vec<String> = launch_command.output_files.iter().filter(|f| Path::new(f).exists()).cloned().collect();
+    for output_file in output_files.iter() {
+        println!("Wrote {0}", output_file);
+        log.push(LogRecord::launcher(&format!("Wrote {0}", output_file)));
+    }
+
+    // The post-exit hook failing doesn't change how the game ended, so errors only get logged
+    if let Some(hook) = &instance.settings.post_exit_hook {
+        let exit_code = match status.code() {
+            Some(code) => code.to_string(),
+            None => String::from(""),
+        };
+        env.set("exit_code", &exit_code);
+        if let Err(e) = run_hook("post-exit", hook, &instance.path, &env.flatten(), &log).await {
+            log.push(LogRecord::launcher(&e));
+        }
+    }
 
+    return Ok(GameExit {
+        version: version.id.clone(),
+        status: status,
+        crash: crash,
+        log_path: Some(log_path),
+        output_files: output_files,
+    });
+}
+
+// Downloads everything the version needs, extracts natives and builds the command to run
+async fn prepare_launch(minecraft_path: &str, version: &MinecraftVersion, instance: &Instance, modes: &LaunchModes, session_time: u64,
+                        env: &mut LayeredEnvironment, natives_path: &str) -> Result<LaunchCommand, String> {
     // Get the version spec for the specified version
     // Downloads minecraft if that version doesn't exist
-    let version_spec = get_version_spec(&minecraft_path, &version).await;
+    let hashes = Arc::new(HashCache::open(minecraft_path, false));
+    let version_spec = get_version_spec(minecraft_path, version, &hashes).await?;
+
+    // Incompatible specs were already refused when getting the spec, but the warnings get shown when launching
+    let report = version_spec.compatibility_report();
 
     env.set("version_name", &version_spec.id);
     env.set("version_type", &version_spec.version_type);
     let assets_root = format!("{0}/assets/", minecraft_path);
     env.set("assets_root", &assets_root);
-    env.set("assets_index_name", &version_spec.assets);
-    let game_assets = format!("{0}/assets/virtual/{1}/", minecraft_path, &version_spec.assets);
+    let assets = version_spec.assets_id()?;
+    env.set("assets_index_name", assets);
+    let game_assets = format!("{0}/assets/virtual/{1}/", minecraft_path, assets);
     env.set("game_assets", &game_assets);
 
     // Check for requirements
-    check_requirements(&minecraft_path, &version_spec).await;
+    check_requirements(minecraft_path, &version_spec, &hashes).await?;
+    hashes.save();
 
     let java_version;
     if let Some(v) = &version_spec.java_version {
@@ -292,84 +390,305 @@ pub async fn launch_minecraft_version(minecraft_path: String, version: Minecraft
     }
 
     // Construct Launch Arguments
-    let natives_dir = tempdir().unwrap();
-    let natives_path = natives_dir.path().to_str().unwrap();
-    let launch_args = construct_launch_args(&minecraft_path, &version_spec, &mut env, natives_path);
+    let launch_args = construct_launch_args(minecraft_path, &version_spec, instance, env, natives_path)?;
+
+    // The wrapper command, if any, runs Java as its own argument
+    let mut command_line = instance.settings.wrapper_command.clone();
+    command_line.push(format!("{0}/runtime/java{1}-{2}-{3}/bin/java", minecraft_path, java_version, get_os(), get_arch()));
+    // The game's own connections can go through the same proxy as the downloads
+    command_line.extend(LauncherConfig::load(minecraft_path).proxy.java_arguments());
+    let (mode_args, output_files) = modes.jvm_args(&instance.path, java_version, session_time);
+    if !output_files.is_empty() {
+        fs::create_dir_all(format!("{0}/debug/", instance.path)).unwrap();
+    }
+    command_line.extend(mode_args);
+    command_line.extend(launch_args);
+
+    let mut env_vars = BTreeMap::new();
+    for (name, value) in instance.settings.env_vars.iter() {
+        match env.resolve_strict(value) {
+            Ok(resolved) => env_vars.insert(name.clone(), resolved),
+            Err(e) => return Err(format!("Couldn't set environment variable {0}, found {1}", name, e)),
+        };
+    }
 
-    // Run Minecraft
-    println!("Launching Minecraft {0}", version.id);
-    let mut java_process = Command::new(format!("{0}/runtime/java{1}-{2}-{3}/bin/java", minecraft_path, java_version, get_os(), get_arch()));
-    java_process.args(launch_args);
-    let status = java_process.status().await.unwrap();
-    println!("Minecraft exited with {0}", status);
-    return status;
+    // The game doesn't need the proxy password, so it doesn't get to see it
+    let mut unset_env_vars = instance.settings.unset_env_vars.clone();
+    if std::env::var_os(PASSWORD_VARIABLE).is_some() && !unset_env_vars.iter().any(|v| v == PASSWORD_VARIABLE) {
+        unset_env_vars.push(String::from(PASSWORD_VARIABLE));
+    }
+
+    return Ok(LaunchCommand {
+        command_line: command_line,
+        working_directory: instance.path.clone(),
+        env_vars: env_vars,
+        unset_env_vars: unset_env_vars,
+        output_files: output_files,
+        warnings: report.warnings,
+    });
+}
+
+// Prepares the game like a normal launch, but writes "{instance}/launch.sh" ("launch.bat" on Windows) instead of starting Java
+// The natives are kept in "{instance}/natives/" so that the script keeps working
+// Hooks aren't run, since the script is meant to be run on its own
+pub async fn dry_launch_minecraft_version(minecraft_path: String, version: MinecraftVersion, mut instance: Instance, modes: LaunchModes, env: Box<LayeredEnvironment>) -> Result<DryLaunch, String> {
+    let mut env = *env;
+    env.set_layer(EnvLayer::Instance, instance.load_environment()?);
+    instance.settings.last_version = Some(version.id.clone());
+    instance.save();
+    env.set("game_directory", &instance.path);
+    env.set("instance_name", &instance.name);
+
+    // Start from an empty natives directory so natives from other versions don't get mixed in
+    let natives_path = format!("{0}/natives", instance.path);
+    if Path::new(&natives_path).exists() {
+        fs::remove_dir_all(&natives_path).map_err(|e| format!("Failed to clear {0}: {1}", natives_path, e))?;
+    }
+    fs::create_dir_all(&natives_path).unwrap();
+    let session_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let launch_command = prepare_launch(&minecraft_path, &version, &instance, &modes, session_time, &mut env, &natives_path).await?;
+
+    let (script_path, script) = match get_os() {
+        "windows" => (format!("{0}/launch.bat", instance.path), launch_command.to_batch_script()),
+        _ => (format!("{0}/launch.sh", instance.path), launch_command.to_shell_script()),
+    };
+    fs::write(&script_path, script).map_err(|e| format!("Failed to write {0}: {1}", script_path, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    println!("Wrote launch script for Minecraft {0} to {1}", version.id, script_path);
+
+    return Ok(DryLaunch {
+        script_path: script_path,
+        command: launch_command,
+    });
 }
 
-async fn get_version_spec(minecraft_path: &str, version: &MinecraftVersion) -> VersionSpec {
-    // Check if the minecraft version is actually downloaded
+// Gets the spec and jar for a version, downloading them if they're missing, damaged or outdated
+async fn get_version_spec(minecraft_path: &str, version: &MinecraftVersion, hashes: &HashCache) -> Result<VersionSpec, String> {
+    // Mojang re-publishes specs in place, ie for the Log4Shell fixes, so check the spec against the version list's hash
     let spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, version.id);
-    if !Path::new(&spec_path).exists() {
-        // Download version if it does not exist
-        println!("Minecraft {0} spec not found", version.id);
-        download_minecraft_version(minecraft_path.to_string(), version.clone()).await;
+    let spec_json;
+    let downloaded;
+    match file_sha1(Path::new(&spec_path)) {
+        Some(sha1) if sha1 == version.sha1 => {
+            spec_json = fs::read_to_string(&spec_path).map_err(|e| format!("Failed to read {0}: {1}", spec_path, e))?;
+            downloaded = false;
+        },
+        Some(_) => {
+            println!("Minecraft {0} spec damaged or outdated, downloading", version.id);
+            spec_json = download_version_spec(version).await?;
+            downloaded = true;
+        },
+        None => {
+            println!("Minecraft {0} spec not found, downloading", version.id);
+            spec_json = download_version_spec(version).await?;
+            downloaded = true;
+        },
     }
+    let spec: VersionSpec = serde_json::from_str(&spec_json).map_err(|e| format!("Failed to parse the spec of Minecraft {0}: {1}", version.id, e))?;
 
-    // TODO: Check sha1 of the spec file
-    let mut spec_file = File::open(spec_path).unwrap();
-    let mut spec_json = String::new();
-    spec_file.read_to_string(&mut spec_json).unwrap();
-    let spec: VersionSpec = serde_json::from_str(&spec_json).unwrap();
+    // Refuse specs that would download or launch wrong, rather than finding out when the game crashes
+    let report = spec.compatibility_report();
+    if !report.is_compatible() {
+        return Err(format!("Minecraft {0} isn't compatible with this launcher:\n{1}", version.id, report.summary()));
+    }
+
+    // Check everything the spec downloads from before downloading any of it
+    for url in spec.download_urls() {
+        net::check_url(url).map_err(|e| format!("Minecraft {0} can't be downloaded safely: {1}", version.id, e))?;
+    }
+
+    // A downloaded spec is only saved once it's known to be usable, so a refused one doesn't replace what was there
+    if downloaded {
+        fs::create_dir_all(format!("{0}/versions/{1}", minecraft_path, version.id)).map_err(|e| format!("Failed to create the directory for Minecraft {0}: {1}", version.id, e))?;
+        write_atomic(Path::new(&spec_path), spec_json.as_bytes())?;
+    }
 
     // Check if the Minecraft jar is damaged
     let jar_path = format!("{0}/versions/{1}/{1}.jar", minecraft_path, version.id);
     let jar_path = Path::new(&jar_path);
-    if !check_file(jar_path, &spec.downloads.client.sha1, spec.downloads.client.size) {
-        println!("Minecraft {0} jar damaged, downloading", version.id);
-        download_minecraft_jar(minecraft_path, &spec).await;
+    let client = spec.client_download()?;
+    if !hashes.check_file(jar_path, &client.sha1, client.size) {
+        println!("Minecraft {0} jar not found or damaged, downloading", version.id);
+        download_minecraft_jar(minecraft_path, &spec.id, client).await?;
         println!("Minecraft {0} jar downloaded", version.id);
     }
 
-    return spec;
+    return Ok(spec);
 }
 
-pub async fn download_minecraft_version(minecraft_path: String, version: MinecraftVersion) -> String {
-    // Create version folder if it doesn't exist
-    if !Path::new(&format!("{0}/versions/{1}/", minecraft_path, version.id)).exists() {
-        fs::create_dir_all(&format!("{0}/versions/{1}", minecraft_path, version.id)).unwrap();
+async fn download_version_spec(version: &MinecraftVersion) -> Result<String, String> {
+    if version.is_custom() {
+        return Err(format!("Minecraft {0} is a custom version, so its spec can't be downloaded", version.id));
     }
+    let version_spec_bytes = net::get_verified(&version.url, &version.sha1).await.map_err(|e| format!("Minecraft {0} spec: {1}", version.id, e))?;
+    return String::from_utf8(version_spec_bytes).map_err(|e| format!("Minecraft {0} spec isn't valid UTF-8: {1}", version.id, e));
+}
 
-    // Download Minecraft version spec
-    println!("Downloading Minecraft version spec");
-    let version_spec_response = reqwest::get(&version.url).await.unwrap();
-    let version_spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, version.id);
-    let mut version_spec_file = File::create(&version_spec_path).unwrap();
-    // Copy text to string first so that I can use it again
-    let version_spec_json = version_spec_response.text().await.unwrap();
-    version_spec_file.write_all(version_spec_json.as_bytes()).unwrap();
-
-    // Deserialize version spec
-    let version_spec: VersionSpec = serde_json::from_str(&version_spec_json).unwrap();
-
-    // Download Minecraft jar
-    println!("Downloading Minecraft {0} jar", version.id);
-    download_minecraft_jar(&minecraft_path, &version_spec).await;
-    println!("Minecraft {0} jar downloaded", version.id);
+// Deep verify hashes every file instead of trusting the hash cache for files that look unchanged
+pub async fn download_minecraft_version(minecraft_path: String, version: MinecraftVersion, deep_verify: bool) -> Result<String, String> {
+    // Get the spec and jar, only downloading what's missing or out of date
+    let hashes = Arc::new(HashCache::open(&minecraft_path, deep_verify));
+    let version_spec = get_version_spec(&minecraft_path, &version, &hashes).await?;
 
     // Check for requirements
-    check_requirements(&minecraft_path, &version_spec).await;
+    check_requirements(&minecraft_path, &version_spec, &hashes).await?;
+    hashes.save();
 
     // Pass on the id (for the downloader tab)
-    return version_spec.id.clone();
+    return Ok(version_spec.id.clone());
+}
+
+// Downloads a version again from scratch, hashing every library and asset it shares with other versions
+pub async fn reinstall_minecraft_version(minecraft_path: String, version: MinecraftVersion) -> Result<String, String> {
+    // Removing a custom version's spec would lose it for good
+    if version.is_custom() {
+        return Err(format!("Minecraft {0} is a custom version, so it can't be reinstalled", version.id));
+    }
+    // The old files are moved aside rather than deleted, so they can be put back if the download fails
+    let version_path = format!("{0}/versions/{1}", minecraft_path, version.id);
+    let old_path = format!("{0}/versions/{1}.old", minecraft_path, version.id);
+    if Path::new(&old_path).exists() {
+        fs::remove_dir_all(&old_path).map_err(|e| format!("Failed to remove {0}: {1}", old_path, e))?;
+    }
+    let moved = Path::new(&version_path).exists();
+    if moved {
+        fs::rename(&version_path, &old_path).map_err(|e| format!("Failed to move {0}: {1}", version_path, e))?;
+    }
+
+    let result = download_minecraft_version(minecraft_path, version, true).await;
+    if moved {
+        match &result {
+            Ok(_) => {
+                if let Err(e) = fs::remove_dir_all(&old_path) {
+                    println!("Failed to remove {0}: {1}", old_path, e);
+                }
+            },
+            Err(_) => {
+                // Put the old version back, dropping whatever the failed download left behind
+                if Path::new(&version_path).exists() {
+                    fs::remove_dir_all(&version_path).map_err(|e| format!("Failed to remove {0}: {1}", version_path, e))?;
+                }
+                fs::rename(&old_path, &version_path).map_err(|e| format!("Failed to restore {0}: {1}", version_path, e))?;
+            },
+        }
+    }
+    return result;
+}
+
+// IDs of the versions with a spec in "{launcher}/versions/", sorted by ID
+pub fn installed_versions(minecraft_path: &str) -> Vec<String> {
+    let mut versions = Vec::new();
+    let versions_path = format!("{0}/versions/", minecraft_path);
+    if let Ok(entries) = fs::read_dir(&versions_path) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let id = entry.file_name().to_string_lossy().into_owned();
+            if Path::new(&format!("{0}/{1}/{1}.json", versions_path, id)).exists() {
+                versions.push(id);
+            }
+        }
+    }
+    versions.sort();
+    return versions;
+}
+
+// Installed versions that aren't in the version list, like modded profiles
+// The spec's own hash stands in for the version list's, since there's nothing to check it against
+pub fn custom_versions(minecraft_path: &str, version_list: &MinecraftVersionList) -> Vec<MinecraftVersion> {
+    let mut versions = Vec::new();
+    for id in installed_versions(minecraft_path) {
+        if version_list.versions.iter().any(|v| v.id == id) {
+            continue;
+        }
+        let spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, id);
+        let spec: Value = match fs::read_to_string(&spec_path).ok().and_then(|spec_json| serde_json::from_str(&spec_json).ok()) {
+            Some(spec) => spec,
+            None => continue,
+        };
+        let field = |name: &str| spec.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        versions.push(MinecraftVersion {
+            id: id.clone(),
+            version_type: field("type"),
+            url: String::new(),
+            time: field("time"),
+            release_time: field("releaseTime"),
+            sha1: file_sha1(Path::new(&spec_path)).unwrap_or_default(),
+            compliance_level: spec.get("complianceLevel").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        });
+    }
+    return versions;
+}
+
+// Reads the spec of an installed version, without downloading anything
+fn read_version_spec(minecraft_path: &str, version_id: &str) -> Result<VersionSpec, String> {
+    let spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, version_id);
+    let mut spec_file = match File::open(&spec_path) {
+        Ok(spec_file) => spec_file,
+        Err(_) => return Err(format!("Minecraft {0} isn't installed", version_id)),
+    };
+    let mut spec_json = String::new();
+    spec_file.read_to_string(&mut spec_json).map_err(|e| format!("Failed to read {0}: {1}", spec_path, e))?;
+    return serde_json::from_str(&spec_json).map_err(|e| format!("Failed to parse {0}: {1}", spec_path, e));
+}
+
+// Checks an installed version's spec for anything the launcher doesn't support
+pub fn check_version_compatibility(minecraft_path: &str, version_id: &str) -> Result<CompatibilityReport, String> {
+    return read_version_spec(minecraft_path, version_id).map(|version| version.compatibility_report());
+}
+
+// Downloads the client's ProGuard mappings if needed, then parses them
+pub async fn get_client_mappings(minecraft_path: String, version_id: String) -> Result<Arc<Mappings>, String> {
+    let version = read_version_spec(&minecraft_path, &version_id)?;
+    let client_mappings = match version.downloads.as_ref().and_then(|d| d.client_mappings.as_ref()) {
+        Some(client_mappings) => client_mappings,
+        None => return Err(format!("Minecraft {0} has no mappings, they only exist for 1.14.4 and later", version_id)),
+    };
+
+    let mappings_path_str = format!("{0}/versions/{1}/{1}-client-mappings.txt", minecraft_path, version.id);
+    let mappings_path = Path::new(&mappings_path_str);
+    let hashes = HashCache::open(&minecraft_path, false);
+    if !hashes.check_file(mappings_path, &client_mappings.sha1, client_mappings.size) {
+        net::check_url(&client_mappings.url)?;
+        println!("Mappings for Minecraft {0} not found or damaged, downloading", version.id);
+        let mappings = net::get_verified(&client_mappings.url, &client_mappings.sha1).await.map_err(|e| format!("Mappings for Minecraft {0}: {1}", version.id, e))?;
+        write_atomic(mappings_path, &mappings)?;
+        println!("Mappings for Minecraft {0} downloaded", version.id);
+    }
+    hashes.save();
+
+    let mappings = fs::read_to_string(mappings_path).map_err(|e| format!("Failed to read {0}: {1}", mappings_path_str, e))?;
+    return Ok(Arc::new(Mappings::parse(&mappings)));
 }
 
-async fn download_minecraft_jar(minecraft_path: &str, version: &VersionSpec) {
-    let minecraft_jar_response = reqwest::get(&version.downloads.client.url).await.unwrap();
-    let minecraft_jar_path = format!("{0}/versions/{1}/{1}.jar", minecraft_path, version.id);
-    let mut minecraft_jar_file = File::create(&minecraft_jar_path).unwrap();
-    minecraft_jar_file.write_all(&minecraft_jar_response.bytes().await.unwrap()).unwrap();
+// Writes a deobfuscated copy of a crash report or log next to the original, and returns its path
+pub async fn deobfuscate_file(minecraft_path: String, version_id: String, path: String) -> Result<String, String> {
+    let mappings = get_client_mappings(minecraft_path, version_id).await?;
+    let contents = match fs::read(&path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("Failed to read {0}: {1}", path, e)),
+    };
+    let deobfuscated = mappings.remap(&String::from_utf8_lossy(&contents));
+
+    let deobfuscated_path = Path::new(&path).with_extension("deobf.txt");
+    fs::write(&deobfuscated_path, deobfuscated).unwrap();
+    return Ok(deobfuscated_path.to_str().unwrap().to_string());
 }
 
-async fn check_requirements(minecraft_path: &str, version: &VersionSpec) {
+async fn download_minecraft_jar(minecraft_path: &str, version_id: &str, client: &Download) -> Result<(), String> {
+    let minecraft_jar = net::get_verified(&client.url, &client.sha1).await.map_err(|e| format!("Minecraft {0} jar: {1}", version_id, e))?;
+    let minecraft_jar_path = format!("{0}/versions/{1}/{1}.jar", minecraft_path, version_id);
+    return write_atomic(Path::new(&minecraft_jar_path), &minecraft_jar);
+}
+
+// Where the Java runtime for a major Java version is installed for the current platform
+pub fn java_runtime_dir(minecraft_path: &str, java_version: u8) -> String {
+    return format!("{0}/runtime/java{1}-{2}-{3}/", minecraft_path, java_version, get_os(), get_arch());
+}
+
+async fn check_requirements(minecraft_path: &str, version: &VersionSpec, hashes: &Arc<HashCache>) -> Result<(), String> {
     let java_version;
     if let Some(v) = &version.java_version {
         java_version = v.major_version;
@@ -379,20 +698,71 @@ async fn check_requirements(minecraft_path: &str, version: &VersionSpec) {
     }
 
     // Check for java installation for the current platform
-    if !Path::new(&format!("{0}/runtime/java{1}-{2}-{3}/", minecraft_path, java_version, get_os(), get_arch())).exists() {
+    if !Path::new(&java_runtime_dir(minecraft_path, java_version)).exists() {
         println!("Java installation not found");
-        download_java(minecraft_path, java_version).await;
+        download_java(minecraft_path, java_version).await?;
     }
 
     // Check for necessary libraries
-    check_minecraft_libraries(minecraft_path, version).await;
+    check_minecraft_libraries(minecraft_path, version, hashes).await?;
 
     // Check for necessary assets
-    check_minecraft_assets(minecraft_path, version).await;
+    check_minecraft_assets(minecraft_path, version, hashes).await?;
+
+    // Check for the logging config
+    check_logging_config(minecraft_path, version, hashes).await?;
+    return Ok(());
+}
+
+// A file that should exist with the given hash, and where to get it from if it doesn't
+pub struct RequiredFile {
+    // What to call the file in messages, ie "Library com.mojang:brigadier:1.0.18"
+    pub id: String,
+    pub path: String,
+    pub url: String,
+    pub sha1: String,
+    pub size: u64,
 }
 
-async fn check_minecraft_libraries(minecraft_path: &str, version: &VersionSpec) {
-    let mut downloaders_vec = Vec::new();
+// Verifies the files on the thread pool, downloading each missing or damaged file as soon as it's found
+// rather than waiting for every file to be checked first
+async fn verify_and_download(mut files: Vec<RequiredFile>, hashes: &Arc<HashCache>) -> Result<(), String> {
+    // Assets with the same contents share a file, which would otherwise be downloaded and written twice at once
+    let mut paths = HashSet::new();
+    files.retain(|file| paths.insert(file.path.clone()));
+
+    let hashes = hashes.clone();
+    let verified = map_parallel(files, move |file| {
+        let valid = hashes.check_file(Path::new(&file.path), &file.sha1, file.size);
+        Ok((file, valid))
+    });
+
+    // Maximum of 25 downloads at a time since too many downloads causes a panic
+    // Returns the ID of each file that got downloaded
+    let mut downloaders = verified.map(|result| async move {
+        let (file, valid) = result?;
+        if valid {
+            println!("{0} already exists", file.id);
+            return Ok(None);
+        }
+        println!("{0} not found or damaged, downloading", file.id);
+
+        // Create folders just to make sure
+        let parent = Path::new(&file.path).parent().unwrap();
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {0}: {1}", parent.display(), e))?;
+        return download_verified(file.path, file.url, file.sha1, file.id).await.map(Some);
+    }).buffer_unordered(25);
+    while let Some(result) = downloaders.next().await {
+        if let Some(id) = result? {
+            println!("{0} downloaded", id);
+        }
+    }
+    return Ok(());
+}
+
+// The library and native jars a version needs on the current platform
+pub fn required_libraries(minecraft_path: &str, version: &VersionSpec) -> Result<Vec<RequiredFile>, String> {
+    let mut files = Vec::new();
     for library in version.libraries.iter() {
         // Check if library rules are satisfied and skip if not
         if library.rules.is_some() && !spec_rules_satisfied(library.rules.as_ref().unwrap()) {
@@ -400,26 +770,15 @@ async fn check_minecraft_libraries(minecraft_path: &str, version: &VersionSpec)
         }
 
         // Check if the library has a general jar
-        if library.downloads.artifact.is_some() {
-            // Check if the library has been downloaded
-            // Uses successive shadowing to please the borrow checker, plus it shows the successive building of the path
-            // Need as_ref before unwrapping the option so as to not consume it
-            let download_artifact = library.downloads.artifact.as_ref().unwrap();
-            let jar_path = download_artifact.path.as_ref().unwrap();
-            let jar_path_str = format!("{0}/libraries/{1}", minecraft_path, jar_path);
-            let jar_path = Path::new(&jar_path_str);
-            if check_file(jar_path, &download_artifact.sha1, download_artifact.size) {
-                println!("Library {0} already exists", library.name);
-            }
-            else {
-                println!("Library {0} not found or damaged, downloading", library.name);
-
-                // Create folders just to make sure
-                fs::create_dir_all(jar_path.parent().unwrap()).unwrap();
-
-                // Download the jar
-                downloaders_vec.push(download_to_file(jar_path_str, download_artifact.url.clone(), format!("Library {0}", library.name)));
-            }
+        if let Some(download_artifact) = &library.downloads.artifact {
+            let jar_path = download_artifact.path.as_ref().ok_or_else(|| format!("Library {0} doesn't say where its jar goes", library.name))?;
+            files.push(RequiredFile {
+                id: format!("Library {0}", library.name),
+                path: format!("{0}/libraries/{1}", minecraft_path, jar_path),
+                url: download_artifact.url.clone(),
+                sha1: download_artifact.sha1.clone(),
+                size: download_artifact.size,
+            });
         }
 
         // Get name of the native's classifier wrappen in an option, returns None if no native
@@ -433,137 +792,197 @@ async fn check_minecraft_libraries(minecraft_path: &str, version: &VersionSpec)
         });
 
         if classifier_name.is_some() {
-            // Check if the native has been downloaded
             // TODO: Classifier name could have variable substitution inside, ie "windows-${arch}", get that working
-            let native_classifier = library.downloads.classifiers.as_ref().unwrap().get(classifier_name.unwrap()).unwrap();
-            let jar_path = native_classifier.path.as_ref().unwrap();
-            let jar_path_str = format!("{0}/libraries/{1}", minecraft_path, jar_path);
-            let jar_path = Path::new(&jar_path_str);
-            if check_file(jar_path, &native_classifier.sha1, native_classifier.size) {
-                println!("Native for {0} already exists", library.name);
-            }
-            else {
-                println!("Native for {0} not found or damaged, downloading", library.name);
+            let native_classifier = match library.downloads.classifiers.as_ref().and_then(|c| c.get(classifier_name.unwrap())) {
+                Some(native_classifier) => native_classifier,
+                None => continue, // Reported by the compatibility check
+            };
+            let jar_path = native_classifier.path.as_ref().ok_or_else(|| format!("Library {0} doesn't say where its native jar goes", library.name))?;
+            files.push(RequiredFile {
+                id: format!("Native for {0}", library.name),
+                path: format!("{0}/libraries/{1}", minecraft_path, jar_path),
+                url: native_classifier.url.clone(),
+                sha1: native_classifier.sha1.clone(),
+                size: native_classifier.size,
+            });
+        }
+    }
 
-                // Create folders just to make sure
-                fs::create_dir_all(jar_path.parent().unwrap()).unwrap();
+    return Ok(files);
+}
 
-                // Download the jar
-                downloaders_vec.push(download_to_file(jar_path_str, native_classifier.url.clone(), format!("Native for {0}", library.name)));
-            }
-        }
+async fn check_minecraft_libraries(minecraft_path: &str, version: &VersionSpec, hashes: &Arc<HashCache>) -> Result<(), String> {
+    let files = required_libraries(minecraft_path, version)?;
+    verify_and_download(files, hashes).await?;
+    println!("All libraries checked and downloaded");
+    return Ok(());
+}
+
+// The objects in an asset index
+pub fn required_assets(minecraft_path: &str, asset_index: &AssetIndex) -> Vec<RequiredFile> {
+    let mut files = Vec::new();
+    for (asset_name, asset_object) in &asset_index.objects {
+        files.push(RequiredFile {
+            id: format!("Asset {0}", asset_name),
+            path: format!("{0}/assets/objects/{1}/{2}", minecraft_path, &asset_object.hash[..2], asset_object.hash),
+            url: format!("https://resources.download.minecraft.net/{0}/{1}", &asset_object.hash[..2], asset_object.hash),
+            sha1: asset_object.hash.clone(),
+            size: asset_object.size,
+        });
     }
+    return files;
+}
 
-    // Poll all downloaders until all downloads are finished
-    // Maximum of 25 downloads at a time since too many downloads causes a panic
-    let mut downloaders = stream::iter(downloaders_vec).map(|func| async { func.await }).buffer_unordered(25);
-    while let Some(id) = downloaders.next().await {
-        println!("{0} downloaded", id);
+// Copies of assets in either virtual or resources for older versions, paired with the object to copy from
+pub fn asset_copies(minecraft_path: &str, assets: &str, asset_index: &AssetIndex) -> Vec<(String, RequiredFile)> {
+    let mut copies = Vec::new();
+    for (asset_name, asset_object) in &asset_index.objects {
+        let asset_path = format!("{0}/assets/objects/{1}/{2}", minecraft_path, &asset_object.hash[..2], asset_object.hash);
+        let asset_url = format!("https://resources.download.minecraft.net/{0}/{1}", &asset_object.hash[..2], asset_object.hash);
+        if asset_index.virtual_assets == Some(true) {
+            copies.push((asset_path.clone(), RequiredFile {
+                id: format!("Virtual asset {0}", asset_name),
+                path: format!("{0}/assets/virtual/{1}/{2}", minecraft_path, assets, asset_name),
+                url: asset_url.clone(),
+                sha1: asset_object.hash.clone(),
+                size: asset_object.size,
+            }));
+        }
+        if asset_index.map_to_resources == Some(true) {
+            copies.push((asset_path, RequiredFile {
+                id: format!("Resource asset {0}", asset_name),
+                path: format!("{0}/resources/{1}", minecraft_path, asset_name),
+                url: asset_url,
+                sha1: asset_object.hash.clone(),
+                size: asset_object.size,
+            }));
+        }
     }
-    println!("All libraries checked and downloaded");
+    return copies;
 }
 
-async fn check_minecraft_assets(minecraft_path: &str, version: &VersionSpec) {
-    let index_path = format!("{0}/assets/indexes/{1}.json", minecraft_path, version.assets);
+async fn check_minecraft_assets(minecraft_path: &str, version: &VersionSpec, hashes: &Arc<HashCache>) -> Result<(), String> {
+    let assets = version.assets_id()?;
+    let index_download = version.asset_index_download()?;
+    let index_path = format!("{0}/assets/indexes/{1}.json", minecraft_path, assets);
     let index_path = Path::new(&index_path);
     let mut index_json = String::new();
 
     // Check if the asset index is downloaded
-    if check_file(index_path, &version.asset_index.sha1, version.asset_index.size) {
+    if hashes.check_file(index_path, &index_download.sha1, index_download.size) {
         // Open asset index if downloaded
         let mut index_file = File::open(index_path).unwrap();
         index_file.read_to_string(&mut index_json).unwrap();
     }
     else {
-        println!("Asset Index {0} not found or damaged, downloading", version.assets);
+        println!("Asset Index {0} not found or damaged, downloading", assets);
 
         // Create folders just to make sure
         fs::create_dir_all(index_path.parent().unwrap()).unwrap();
 
         // Download the asset index
-        let index_response = reqwest::get(&version.asset_index.url).await.unwrap();
-        let mut index_file = File::create(index_path).unwrap();
-        index_json = index_response.text().await.unwrap();
-        index_file.write_all(index_json.as_bytes()).unwrap();
+        let index_bytes = net::get_verified(&index_download.url, &index_download.sha1).await.map_err(|e| format!("Asset Index {0}: {1}", assets, e))?;
+        index_json = String::from_utf8(index_bytes).map_err(|e| format!("Asset Index {0} isn't valid UTF-8: {1}", assets, e))?;
+        write_atomic(index_path, index_json.as_bytes())?;
     }
 
     // Deserialize asset index
     let asset_index: AssetIndex = serde_json::from_str(&index_json).unwrap();
 
     // Check and download all assets
-    let mut downloaders_vec = Vec::new();
-    for (asset_name, asset_object) in &asset_index.objects {
-        let asset_path_str = format!("{0}/assets/objects/{1}/{2}", minecraft_path, &asset_object.hash[..2], asset_object.hash);
-        let asset_path = Path::new(&asset_path_str);
-
-        if check_file(asset_path, &asset_object.hash, asset_object.size) {
-            println!("Asset {0} already exists", asset_name);
-        }
-        else {
-            println!("Asset {0} not found or damaged, downloading", asset_name);
-
-            // Create folders just to make sure
-            fs::create_dir_all(asset_path.parent().unwrap()).unwrap();
-
-            // Download the asset
-            let asset_url = format!("http://resources.download.minecraft.net/{0}/{1}", &asset_object.hash[..2], asset_object.hash);
-            downloaders_vec.push(download_to_file(asset_path_str, asset_url, asset_name.to_string()));
-        }
-    }
-
-    // Poll all downloaders until all downloads are finished
-    // Maximum of 25 downloads at a time since too many downloads causes a panic
-    let mut downloaders = stream::iter(downloaders_vec).map(|func| async { func.await }).buffer_unordered(25);
-    while let Some(id) = downloaders.next().await {
-        println!("Asset {0} downloaded", id);
-    }
-
-    // Copy assets to appropriate directories if needed
-    for (asset_name, asset_object) in &asset_index.objects {
-        let asset_path = format!("{0}/assets/objects/{1}/{2}", minecraft_path, &asset_object.hash[..2], asset_object.hash);
+    verify_and_download(required_assets(minecraft_path, &asset_index), hashes).await?;
+
+    // Link assets into virtual or resources for older versions
+    // Linking is blocking work too, so it happens on the thread pool
+    let copies = asset_copies(minecraft_path, assets, &asset_index);
+    let link_mode = LauncherConfig::load(minecraft_path).asset_links;
+    let copy_hashes = hashes.clone();
+    let mut copied = map_parallel(copies, move |(asset_path, file)| {
         let asset_path = Path::new(&asset_path);
-
-        // Copy to either virtual or resources for older versions
-        if asset_index.virtual_assets == Some(true) {
-            let virtual_path = format!("{0}/assets/virtual/{1}/{2}", minecraft_path, version.assets, asset_name);
-            let virtual_path = Path::new(&virtual_path);
-
-            if check_file(virtual_path, &asset_object.hash, asset_object.size) {
-                println!("Virtual asset {0} already exists", asset_name);
+        let file_path = Path::new(&file.path);
+        if copy_hashes.check_file(file_path, &file.sha1, file.size) {
+            // Older launches copied every asset, so link those copies to free up the space
+            if link_mode.converts_copies() && convert_to_hardlink(asset_path, file_path) {
+                println!("{0} already exists, converted to a hardlink", file.id);
             }
             else {
-                println!("Virtual asset {0} not found or damaged, copying", asset_name);
-
-                // Create folders just to make sure
-                fs::create_dir_all(virtual_path.parent().unwrap()).unwrap();
-
-                // Copy the asset
-                fs::copy(&asset_path, &virtual_path).unwrap();
+                println!("{0} already exists", file.id);
             }
+            return Ok(());
         }
+        println!("{0} not found or damaged, linking", file.id);
 
-        if asset_index.map_to_resources == Some(true) {
-            let resource_path = format!("{0}/resources/{1}", minecraft_path, asset_name);
-            let resource_path = Path::new(&resource_path);
+        // Create folders just to make sure
+        let parent = file_path.parent().unwrap();
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {0}: {1}", parent.display(), e))?;
+
+        // Link or copy the asset
+        let placement = place_file(asset_path, file_path, link_mode).map_err(|e| format!("Failed to link {0} to {1}: {2}", file.id, file.path, e))?;
+        println!("{0} {1}", file.id, placement);
+        return Ok(());
+    });
+    while let Some(result) = copied.next().await {
+        result?;
+    }
+    println!("All assets checked and downloaded");
+    return Ok(());
+}
 
-            if check_file(resource_path, &asset_object.hash, asset_object.size) {
-                println!("Resource asset {0} already exists", asset_name);
-            }
-            else {
-                println!("Resource asset {0} not found or damaged, copying", asset_name);
+// The Log4j config from the spec, if it has one
+pub fn required_logging_config(minecraft_path: &str, version: &VersionSpec) -> Option<RequiredFile> {
+    let logging = version.logging.as_ref().and_then(|l| l.client.as_ref())?;
+    return Some(RequiredFile {
+        id: format!("Logging config {0}", logging.file.id),
+        path: format!("{0}/assets/log_configs/{1}", minecraft_path, logging.file.id),
+        url: logging.file.url.clone(),
+        sha1: logging.file.sha1.clone(),
+        size: logging.file.size,
+    });
+}
 
-                // Create folders just to make sure
-                fs::create_dir_all(resource_path.parent().unwrap()).unwrap();
+async fn check_logging_config(minecraft_path: &str, version: &VersionSpec, hashes: &HashCache) -> Result<(), String> {
+    let config = match required_logging_config(minecraft_path, version) {
+        Some(config) => config,
+        None => return Ok(()),
+    };
 
-                // Copy the asset
-                fs::copy(&asset_path, &resource_path).unwrap();
-            }
-        }
+    let config_path = Path::new(&config.path);
+    if hashes.check_file(config_path, &config.sha1, config.size) {
+        println!("{0} already exists", config.id);
     }
-    println!("All assets checked and downloaded");
+    else {
+        println!("{0} not found or damaged, downloading", config.id);
+
+        // Create folders just to make sure
+        let parent = config_path.parent().unwrap();
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {0}: {1}", parent.display(), e))?;
+
+        // Download the config
+        let id = download_verified(config.path.clone(), config.url.clone(), config.sha1.clone(), config.id.clone()).await?;
+        println!("{0} downloaded", id);
+    }
+    return Ok(());
 }
 
-fn construct_launch_args(minecraft_path: &str, version: &VersionSpec, env: &mut Environment, natives_dir: &str) -> Vec<String> {
+fn construct_logging_arg(minecraft_path: &str, version: &VersionSpec, instance: &Instance) -> Option<String> {
+    let logging = version.logging.as_ref().and_then(|l| l.client.as_ref());
+
+    // The instance's own config takes priority over the one from the spec
+    let config_path = match &instance.settings.logging_config {
+        Some(path) => path.clone(),
+        None => format!("{0}/assets/log_configs/{1}", minecraft_path, logging?.file.id),
+    };
+    let argument = match logging {
+        Some(logging) => logging.argument.as_str(),
+        None => "-Dlog4j.configurationFile=${path}",
+    };
+
+    let mut logging_env = Environment::new();
+    logging_env.set_literal("path", &config_path);
+    return Some(logging_env.resolve(argument));
+}
+
+fn construct_launch_args(minecraft_path: &str, version: &VersionSpec, instance: &Instance, env: &mut LayeredEnvironment, natives_dir: &str) -> Result<Vec<String>, String> {
     // Construct classpath and natives directory
     // TODO: Move classpath construction to library
     let mut classpath = String::new();
@@ -578,7 +997,7 @@ fn construct_launch_args(minecraft_path: &str, version: &VersionSpec, env: &mut
             // Uses successive shadowing to please the borrow checker, plus it shows the successive building of the path
             // Need as_ref before unwrapping the option so as to not consume it
             let download_artifact = library.downloads.artifact.as_ref().unwrap();
-            let jar_path = download_artifact.path.as_ref().unwrap();
+            let jar_path = download_artifact.path.as_ref().ok_or_else(|| format!("Library {0} doesn't say where its jar goes", library.name))?;
             let jar_path = format!("{0}/libraries/{1}", minecraft_path, jar_path);
 
             // Add to the classpath
@@ -604,15 +1023,17 @@ fn construct_launch_args(minecraft_path: &str, version: &VersionSpec, env: &mut
         if classifier_name.is_some() {
             // Check if the native has been downloaded
             // TODO: Classifier name could have variable substitution inside, ie "windows-${arch}", get that working
-            let native_classifier = library.downloads.classifiers.as_ref().unwrap().get(classifier_name.unwrap()).unwrap();
-            let jar_path = native_classifier.path.as_ref().unwrap();
+            let native_classifier = match library.downloads.classifiers.as_ref().and_then(|c| c.get(classifier_name.unwrap())) {
+                Some(native_classifier) => native_classifier,
+                None => continue, // Reported by the compatibility check
+            };
+            let jar_path = native_classifier.path.as_ref().ok_or_else(|| format!("Library {0} doesn't say where its native jar goes", library.name))?;
             let jar_path = format!("{0}/libraries/{1}", minecraft_path, jar_path);
-            let jar_path = Path::new(&jar_path);
 
             // Extract into the natives directory
-            let natives_jar = File::open(jar_path).unwrap();
-            let mut archive = ZipArchive::new(natives_jar).unwrap();
-            archive.extract(natives_dir).unwrap();
+            let natives_jar = File::open(&jar_path).map_err(|e| format!("Failed to open {0}: {1}", jar_path, e))?;
+            let mut archive = ZipArchive::new(natives_jar).map_err(|e| format!("Failed to read {0}: {1}", jar_path, e))?;
+            archive.extract(natives_dir).map_err(|e| format!("Failed to extract {0}: {1}", jar_path, e))?;
             println!("Extracted native for {0}", library.name);
         }
     }
@@ -620,75 +1041,94 @@ fn construct_launch_args(minecraft_path: &str, version: &VersionSpec, env: &mut
     classpath += &jar_path; // Don't forget to add the Minecraft jar itself
     env.set("classpath", &classpath);
     env.set("natives_directory", natives_dir);
-
-    // Construct the launch arguments
-    let mut launch_args = Vec::<String>::new();
-    if version.arguments.is_some() {
-        for arg in version.arguments.as_ref().unwrap().jvm.iter() {
-            match arg {
-                Argument::Static(arg_str) => launch_args.push(arg_str.to_string()),
-                Argument::Dynamic(dynamic_arg) => {
-                    if spec_rules_satisfied(&dynamic_arg.rules) {
-                        match &dynamic_arg.value {
-                            SingleOrVec::Single(dynamic_arg_value) => launch_args.push(dynamic_arg_value.to_string()),
-                            SingleOrVec::Vector(dynamic_arg_vec) => {
-                                for dynamic_arg_value in dynamic_arg_vec.iter() {
-                                    launch_args.push(dynamic_arg_value.to_string());
-                                }
-                            },
-                        }
-                    }
-                },
-            }
-        }
-        launch_args.push(version.main_class.clone());
-        for arg in version.arguments.as_ref().unwrap().game.iter() {
-            match arg {
-                Argument::Static(arg_str) => launch_args.push(arg_str.to_string()),
-                Argument::Dynamic(dynamic_arg) => {
-                    if spec_rules_satisfied(&dynamic_arg.rules) {
-                        match &dynamic_arg.value {
-                            SingleOrVec::Single(dynamic_arg_value) => launch_args.push(dynamic_arg_value.to_string()),
-                            SingleOrVec::Vector(dynamic_arg_vec) => {
-                                for dynamic_arg_value in dynamic_arg_vec.iter() {
-                                    launch_args.push(dynamic_arg_value.to_string());
-                                }
-                            },
-                        }
-                    }
-                },
-            }
-        }
+    // Used by modded version specs to refer to libraries in their own arguments
+    env.set("library_directory", &format!("{0}/libraries", minecraft_path));
+    env.set("classpath_separator", if get_os() == "windows" { ";" } else { ":" });
+
+    // Collect the JVM and game arguments from the version spec
+    let mut jvm_args = Vec::<String>::new();
+    let mut game_args = Vec::<String>::new();
+    if let Some(arguments) = &version.arguments {
+        push_spec_args(&arguments.jvm, &mut jvm_args);
+        push_spec_args(&arguments.game, &mut game_args);
     }
     else {
+        let minecraft_arguments = version.minecraft_arguments.as_ref().ok_or_else(|| format!("{0} doesn't give any game arguments", version.id))?;
         // Hardcoded JVM arguments, since they're not specified in the version spec
         if get_os() == "windows" {
-            launch_args.push("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump".to_string());
+            jvm_args.push("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump".to_string());
             // TODO: Do -Dos.name=Windows 10 and -Dos.version=10.0 if Windows 10
         }
         if get_os() == "macos" {
-            launch_args.push("-XstartOnFirstThread".to_string());
+            jvm_args.push("-XstartOnFirstThread".to_string());
         }
         if get_arch() == "x86" {
-            launch_args.push("-Xss1M".to_string());
+            jvm_args.push("-Xss1M".to_string());
         }
-        launch_args.push("-Djava.library.path=${natives_directory}".to_string());
-        launch_args.push("-Dminecraft.launcher.brand=${launcher_name}".to_string());
-        launch_args.push("-Dminecraft.launcher.version=${launcher_version}".to_string());
-        launch_args.push(format!("-Dminecraft.client.jar={0}", jar_path).to_string());
-        launch_args.push("-cp".to_string());
-        launch_args.push("${classpath}".to_string());
-        launch_args.push(version.main_class.clone());
-        let mut minecraft_args: Vec<String> = version.minecraft_arguments.as_ref().unwrap().split(" ").map(|s| s.to_string()).collect();
-        launch_args.append(&mut minecraft_args);
+        jvm_args.push("-Djava.library.path=${natives_directory}".to_string());
+        jvm_args.push("-Dminecraft.launcher.brand=${launcher_name}".to_string());
+        jvm_args.push("-Dminecraft.launcher.version=${launcher_version}".to_string());
+        jvm_args.push(format!("-Dminecraft.client.jar={0}", jar_path).to_string());
+        jvm_args.push("-cp".to_string());
+        jvm_args.push("${classpath}".to_string());
+        game_args = minecraft_arguments.split(" ").map(|s| s.to_string()).collect();
     }
 
-    // Replace ${config} variables with the values
+    // Construct the launch arguments in this order:
+    // spec JVM args, instance memory, instance JVM args, logging config, main class, spec game args, instance game args
+    // The instance's arguments come after the spec's so that they take priority
+    let settings = &instance.settings;
+    let mut launch_args = jvm_args;
+    if let Some(min_memory) = settings.min_memory {
+        launch_args.push(format!("-Xms{0}M", min_memory));
+    }
+    if let Some(max_memory) = settings.max_memory {
+        launch_args.push(format!("-Xmx{0}M", max_memory));
+    }
+    launch_args.extend(settings.jvm_args.iter().cloned());
+    if let Some(logging_arg) = construct_logging_arg(minecraft_path, version, instance) {
+        launch_args.push(logging_arg);
+    }
+    launch_args.push(version.main_class.clone());
+    launch_args.append(&mut game_args);
+    launch_args.extend(settings.game_args.iter().cloned());
+
+    // Replace ${config} variables with the values, reporting every variable that's missing
+    let env = env.flatten();
+    let mut errors = ResolveError::default();
     for arg in launch_args.iter_mut() {
-        *arg = env.resolve(arg);
+        match env.resolve_strict(arg) {
+            Ok(resolved) => *arg = resolved,
+            Err(e) => errors.extend(e),
+        }
     }
+    if !errors.is_empty() {
+        return Err(format!("Couldn't construct the launch arguments, found {0}", errors));
+    }
+
+    return Ok(launch_args);
+}
 
-    return launch_args;
+fn push_spec_args(spec_args: &[Argument], launch_args: &mut Vec<String>) {
+    for arg in spec_args.iter() {
+        match arg {
+            Argument::Static(arg_str) => launch_args.push(arg_str.to_string()),
+            Argument::Dynamic(dynamic_arg) => {
+                if spec_rules_satisfied(&dynamic_arg.rules) {
+                    match &dynamic_arg.value {
+                        SingleOrVec::Single(dynamic_arg_value) => launch_args.push(dynamic_arg_value.to_string()),
+                        SingleOrVec::Vector(dynamic_arg_vec) => {
+                            for dynamic_arg_value in dynamic_arg_vec.iter() {
+                                launch_args.push(dynamic_arg_value.to_string());
+                            }
+                        },
+                    }
+                }
+            },
+            // Reported by the compatibility check
+            Argument::Unknown(_) => {},
+        }
+    }
 }
 
 fn spec_rules_satisfied(rules: &Vec<Rule>) -> bool {
@@ -697,7 +1137,8 @@ fn spec_rules_satisfied(rules: &Vec<Rule>) -> bool {
         let allow_match = match rule.action.as_str() {
             "allow" => true,
             "disallow" => false,
-            _ => panic!("Unknown rule action"),
+            // Reported by the compatibility check, and never matching is the safest guess
+            _ => return false,
         };
 
         // Check if os is matched
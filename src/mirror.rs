@@ -0,0 +1,105 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+// The hosts the launcher downloads from, with what comes from each
+pub const KNOWN_HOSTS: [(&str, &str); 7] = [
+    ("piston-meta.mojang.com", "Version manifest, version specs and asset indexes"),
+    ("launchermeta.mojang.com", "Version specs and asset indexes of older versions"),
+    ("piston-data.mojang.com", "Client jars, mappings and logging configs"),
+    ("launcher.mojang.com", "Client jars, mappings and logging configs of older versions"),
+    ("libraries.minecraft.net", "Libraries"),
+    ("resources.download.minecraft.net", "Assets"),
+    ("api.adoptium.net", "Java runtimes"),
+];
+
+// Sends everything from a host to a mirror instead
+// ie host "libraries.minecraft.net" and url "https://mirror.example.com/maven" turns
+// "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar" into
+// "https://mirror.example.com/maven/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MirrorRule {
+    pub host: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct MirrorConfig {
+    pub rules: Vec<MirrorRule>,
+    // Download from the original host when a mirror fails or doesn't have the file
+    pub fallback_to_origin: bool,
+}
+
+impl MirrorConfig {
+    // Replaces any existing rule for the host
+    pub fn set(&mut self, host: &str, url: &str) -> Result<(), String> {
+        let parsed = Url::parse(url).map_err(|e| format!("'{0}' isn't a valid URL: {1}", url, e))?;
+        if parsed.cannot_be_a_base() || parsed.query().is_some() {
+            return Err(format!("'{0}' can't be used as a mirror, it needs to be a URL that paths can be added to", url));
+        }
+        self.remove(host);
+        self.rules.push(MirrorRule {
+            host: host.to_lowercase(),
+            url: url.trim_end_matches('/').to_string(),
+        });
+        return Ok(());
+    }
+
+    // Returns whether there was a rule to remove
+    pub fn remove(&mut self, host: &str) -> bool {
+        let count = self.rules.len();
+        self.rules.retain(|rule| !rule.host.eq_ignore_ascii_case(host));
+        return self.rules.len() != count;
+    }
+
+    // The mirrored URL, or None if no rule applies to it
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let parsed = Url::parse(url).ok()?;
+        let host = parsed.host_str()?;
+        let rule = self.rules.iter().find(|rule| rule.host.eq_ignore_ascii_case(host))?;
+        let mut rewritten = format!("{0}{1}", rule.url, parsed.path());
+        if let Some(query) = parsed.query() {
+            rewritten += &format!("?{0}", query);
+        }
+        return Some(rewritten);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirrors(host: &str, url: &str) -> MirrorConfig {
+        let mut mirrors = MirrorConfig::default();
+        mirrors.set(host, url).unwrap();
+        return mirrors;
+    }
+
+    #[test]
+    fn rewrite_keeps_path_and_query() {
+        let mirrors = mirrors("libraries.minecraft.net", "https://mirror.example.com/maven");
+        assert_eq!(mirrors.rewrite("https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"),
+                   Some(String::from("https://mirror.example.com/maven/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar")));
+        assert_eq!(mirrors.rewrite("https://LIBRARIES.minecraft.net/a.jar?token=abc&x=1"),
+                   Some(String::from("https://mirror.example.com/maven/a.jar?token=abc&x=1")));
+        assert_eq!(mirrors.rewrite("https://piston-data.mojang.com/a.jar"), None);
+        assert_eq!(mirrors.rewrite("not a url"), None);
+    }
+
+    #[test]
+    fn rewrite_ignores_trailing_slash_on_mirror() {
+        let mirrors = mirrors("resources.download.minecraft.net", "https://mirror.example.com/assets/");
+        assert_eq!(mirrors.rewrite("https://resources.download.minecraft.net/ab/abcdef"),
+                   Some(String::from("https://mirror.example.com/assets/ab/abcdef")));
+        assert_eq!(mirrors.rewrite("https://resources.download.minecraft.net/"),
+                   Some(String::from("https://mirror.example.com/assets/")));
+    }
+
+    #[test]
+    fn set_refuses_urls_that_cant_be_a_base() {
+        let mut mirrors = MirrorConfig::default();
+        assert!(mirrors.set("libraries.minecraft.net", "https://mirror.example.com/?x=1").is_err());
+        assert!(mirrors.set("libraries.minecraft.net", "mailto:someone@example.com").is_err());
+        assert!(mirrors.rules.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+use lazy_static::lazy_static;
+use reqwest::{Certificate, Client, Response};
+use reqwest::redirect::Policy;
+
+use crate::allowlist::HostAllowlist;
+use crate::config::LauncherConfig;
+use crate::mirror::MirrorConfig;
+use crate::util::sha1_hex;
+
+// The parts of the launcher config that decide where and how downloads happen
+struct NetConfig {
+    mirrors: MirrorConfig,
+    allowlist: HostAllowlist,
+    // What the client gets built from
+    launcher_config: LauncherConfig,
+    // Shared by every download so that connections get reused
+    // None until it could be set up, so a proxy that can't be found right now doesn't break downloads for the whole session
+    // Until then, downloads fail with why instead of going around the proxy
+    client: Option<Client>,
+}
+
+impl NetConfig {
+    fn new(config: &LauncherConfig) -> NetConfig {
+        NetConfig {
+            mirrors: config.mirrors.clone(),
+            allowlist: config.allowlist.clone(),
+            launcher_config: config.clone(),
+            client: None,
+        }
+    }
+}
+
+// Redirects count towards the limit reqwest uses by default
+const MAX_REDIRECTS: usize = 10;
+
+// Times a file with a known hash gets downloaded before giving up on it
+const DOWNLOAD_ATTEMPTS: usize = 3;
+
+lazy_static! {
+    // Set from the launcher config on startup, so that every download goes through the same client, mirrors and checks
+    static ref NET_CONFIG: RwLock<NetConfig> = RwLock::new(NetConfig::new(&LauncherConfig::default()));
+}
+
+pub fn configure(config: &LauncherConfig) {
+    let mut net_config = NetConfig::new(config);
+    // Set the client up right away to report problems early, it's tried again on the next download if it fails
+    match build_client(config) {
+        Ok(client) => net_config.client = Some(client),
+        Err(e) => println!("{0}", e),
+    }
+    *NET_CONFIG.write().unwrap() = net_config;
+}
+
+// The shared client, setting it up if it couldn't be before
+fn client() -> Result<Client, String> {
+    if let Some(client) = &NET_CONFIG.read().unwrap().client {
+        return Ok(client.clone());
+    }
+    let mut config = NET_CONFIG.write().unwrap();
+    if let Some(client) = &config.client {
+        return Ok(client.clone());
+    }
+    let client = build_client(&config.launcher_config)?;
+    config.client = Some(client.clone());
+    return Ok(client);
+}
+
+fn build_client(config: &LauncherConfig) -> Result<Client, String> {
+    // Checks where every redirect goes too, so that an allowed host can't send a download somewhere else
+    let mut builder = Client::builder()
+        .redirect(Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            let config = NET_CONFIG.read().unwrap();
+            match config.allowlist.check_redirect(attempt.url(), &config.mirrors) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        }));
+
+    // Without a proxy in the config, the HTTP_PROXY and HTTPS_PROXY environment variables are still used
+    if let Some(proxy) = config.proxy.build()? {
+        builder = builder.proxy(proxy);
+    }
+    for path in config.ca_certificates.iter() {
+        let pem = fs::read(path).map_err(|e| format!("Failed to read the certificate {0}: {1}", path, e))?;
+        let certificate = Certificate::from_pem(&pem).map_err(|e| format!("Failed to parse the certificate {0}: {1}", path, e))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    if config.connect_timeout > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(config.connect_timeout));
+    }
+    if config.download_timeout > 0 {
+        builder = builder.timeout(Duration::from_secs(config.download_timeout));
+    }
+    return builder.build().map_err(|e| format!("Failed to set up downloading: {0}", e));
+}
+
+// The URL a download will actually use, upgraded to HTTPS, or why it isn't allowed
+pub fn check_url(url: &str) -> Result<String, String> {
+    let config = NET_CONFIG.read().unwrap();
+    return config.allowlist.check(url, &config.mirrors);
+}
+
+// Fetches a URL, from its mirror if one is configured
+// If the mirror fails or doesn't have it, the original host is tried when falling back is enabled
+pub async fn get(original: &str) -> Result<Response, String> {
+    let url = check_url(original)?;
+    let (mirrored, fallback) = {
+        let config = NET_CONFIG.read().unwrap();
+        (config.mirrors.rewrite(&url), config.mirrors.fallback_to_origin)
+    };
+    let mirrored = match mirrored {
+        Some(mirrored) => mirrored,
+        None => return fetch_upgraded(original, &url).await,
+    };
+    if !fallback {
+        return fetch(&mirrored).await;
+    }
+
+    match fetch(&mirrored).await {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            println!("Failed to download {0} from the mirror, trying {1} instead: {2}", mirrored, url, e);
+            fetch_upgraded(original, &url).await
+        },
+    }
+}
+
+// Downloads a file that has to match the given SHA-1, trying again if it doesn't
+// Nothing is returned unless it matches, so a bad download never replaces a good file
+pub async fn get_verified(url: &str, sha1: &str) -> Result<Vec<u8>, String> {
+    let mut error = String::new();
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        let bytes = match get(url).await {
+            Ok(response) => response.bytes().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match bytes {
+            Ok(bytes) if sha1_hex(&bytes) == sha1 => return Ok(bytes.to_vec()),
+            Ok(bytes) => error = format!("got {0} instead of {1}", sha1_hex(&bytes), sha1),
+            Err(e) => error = e,
+        }
+        if attempt < DOWNLOAD_ATTEMPTS {
+            println!("Failed to download {0}, trying again: {1}", url, error);
+        }
+    }
+    return Err(format!("Failed to download {0} after {1} attempts: {2}", url, DOWNLOAD_ATTEMPTS, error));
+}
+
+// Fetches a URL that was upgraded to HTTPS, using the original HTTP URL if the upgrade fails and the user allowed its host
+async fn fetch_upgraded(original: &str, upgraded: &str) -> Result<Response, String> {
+    let result = fetch(upgraded).await;
+    if let Err(e) = &result {
+        let allows_http = NET_CONFIG.read().unwrap().allowlist.allows_http(original);
+        if allows_http {
+            println!("Failed to download {0} over HTTPS, trying HTTP instead: {1}", upgraded, e);
+            return fetch(original).await;
+        }
+    }
+    return result;
+}
+
+// Error responses count as failures, so that an error page never gets saved as the file
+async fn fetch(url: &str) -> Result<Response, String> {
+    let client = client()?;
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    return response.error_for_status().map_err(|e| e.to_string());
+}
@@ -0,0 +1,80 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use futures::channel::mpsc::{self, UnboundedReceiver};
+
+// Checking files is mostly waiting on the disk, so more threads than this doesn't help
+const MAX_THREADS: usize = 8;
+
+pub fn thread_count() -> usize {
+    return thread::available_parallelism().map_or(4, |n| n.get()).min(MAX_THREADS);
+}
+
+// Runs a blocking function over every item on a bounded pool of threads
+// Results are sent back as each one finishes, in no particular order,
+// so async code can act on them while the rest are still running
+// A panic in the function comes back as an error for that item instead of silently ending the results early
+pub fn map_parallel<T, R, F>(items: Vec<T>, f: F) -> UnboundedReceiver<Result<R, String>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Result<R, String> + Send + Sync + 'static,
+{
+    let (sender, receiver) = mpsc::unbounded();
+    let threads = thread_count().min(items.len());
+    let queue = Arc::new(Mutex::new(items.into_iter()));
+    let f = Arc::new(f);
+    for _ in 0..threads {
+        let queue = queue.clone();
+        let sender = sender.clone();
+        let f = f.clone();
+        thread::spawn(move || {
+            loop {
+                // Take the lock only long enough to grab the next item
+                let item = queue.lock().unwrap().next();
+                let item = match item {
+                    Some(item) => item,
+                    None => break,
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| f(item))).unwrap_or_else(|e| Err(panic_message(e)));
+                // Stop early if nobody is listening anymore
+                if sender.unbounded_send(result).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    // The receiver finishes once every thread has dropped its sender
+    return receiver;
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    let message = match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+    };
+    return format!("A worker thread panicked: {0}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+    use futures::stream::StreamExt;
+
+    #[test]
+    fn map_parallel_reports_errors_and_panics() {
+        let results: Vec<Result<u32, String>> = task::block_on(map_parallel((0..50).collect(), |i: u32| {
+            match i {
+                7 => panic!("item {0} is broken", i),
+                13 => Err(String::from("item 13 failed")),
+                _ => Ok(i * 2),
+            }
+        }).collect());
+        assert_eq!(results.len(), 50);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 48);
+        assert!(results.contains(&Err(String::from("A worker thread panicked: item 7 is broken"))));
+        assert!(results.contains(&Err(String::from("item 13 failed"))));
+    }
+}
@@ -0,0 +1,126 @@
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use async_std::process::Child;
+
+// The process of a game, once it has been started
+struct GameProcess {
+    pid: u32,
+    start_time: SystemTime,
+    child: Child,
+}
+
+// A game launched by the launcher, registered from the start of the launch so that it can be killed while still being prepared
+pub struct RunningGame {
+    // Identifies the game in the registry, since there's no PID until the process starts
+    pub id: u64,
+    pub instance: String,
+    pub version: String,
+    process: Mutex<Option<GameProcess>>,
+    killed: AtomicBool,
+}
+
+impl RunningGame {
+    pub fn pid(&self) -> Option<u32> {
+        return self.process.lock().unwrap().as_ref().map(|p| p.pid);
+    }
+
+    pub fn start_time(&self) -> Option<SystemTime> {
+        return self.process.lock().unwrap().as_ref().map(|p| p.start_time);
+    }
+
+    // None while the launch is still being prepared
+    pub fn uptime(&self) -> Option<Duration> {
+        return self.start_time().map(|t| t.elapsed().unwrap_or_default());
+    }
+
+    // Stops the launch if the game hasn't started yet, otherwise kills it
+    pub fn kill(&self) {
+        self.killed.store(true, Ordering::SeqCst);
+        if let Some(process) = self.process.lock().unwrap().as_mut() {
+            // The game may have exited on its own in the meantime, so this isn't fatal
+            if let Err(e) = process.child.kill() {
+                println!("Failed to kill process {0}: {1}", process.pid, e);
+            }
+        }
+    }
+
+    pub fn is_killed(&self) -> bool {
+        return self.killed.load(Ordering::SeqCst);
+    }
+
+    // Hands the started process to the game, killing it straight away if the launch was stopped in the meantime
+    pub fn attach(&self, child: Child) -> u32 {
+        let pid = child.id();
+        *self.process.lock().unwrap() = Some(GameProcess {
+            pid: pid,
+            start_time: SystemTime::now(),
+            child: child,
+        });
+        if self.is_killed() {
+            self.kill();
+        }
+        return pid;
+    }
+
+    // None while it's still running, or if its status can't be checked right now
+    pub fn try_status(&self) -> Option<ExitStatus> {
+        return self.process.lock().unwrap().as_mut().and_then(|p| p.child.try_status().ok().flatten());
+    }
+}
+
+// Keeps track of every game that's currently running or being launched
+pub struct ProcessRegistry {
+    games: Mutex<Vec<Arc<RunningGame>>>,
+    next_id: AtomicU64,
+}
+
+impl ProcessRegistry {
+    pub fn new() -> ProcessRegistry {
+        ProcessRegistry {
+            games: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn register(&self, instance: &str, version: &str) -> Arc<RunningGame> {
+        let game = Arc::new(RunningGame {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            instance: instance.to_string(),
+            version: version.to_string(),
+            process: Mutex::new(None),
+            killed: AtomicBool::new(false),
+        });
+        self.games.lock().unwrap().push(game.clone());
+        return game;
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.games.lock().unwrap().retain(|g| g.id != id);
+    }
+
+    pub fn list(&self) -> Vec<Arc<RunningGame>> {
+        return self.games.lock().unwrap().clone();
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<RunningGame>> {
+        return self.games.lock().unwrap().iter().find(|g| g.id == id).cloned();
+    }
+
+    pub fn is_running(&self, instance: &str) -> bool {
+        return self.games.lock().unwrap().iter().any(|g| g.instance == instance);
+    }
+
+    pub fn kill_all(&self) {
+        for game in self.list() {
+            game.kill();
+        }
+    }
+}
+
+// Formats a duration as HH:MM:SS
+pub fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    return format!("{0:02}:{1:02}:{2:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+}
@@ -0,0 +1,144 @@
+use std::net::ToSocketAddrs;
+use reqwest::{Proxy, Url};
+use serde::{Deserialize, Serialize};
+
+// Environment variable the proxy password is read from instead, so that it doesn't have to be saved anywhere
+pub const PASSWORD_VARIABLE: &str = "MINELAUNCH_PROXY_PASSWORD";
+
+// A proxy every download goes through, and optionally the game too
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyConfig {
+    // ie "http://proxy.example.com:8080", "https://proxy.example.com" or "socks5://127.0.0.1:1080", no proxy is used if empty
+    // socks5h:// also has the proxy look up the hosts being downloaded from
+    pub url: String,
+    // Login for the proxy, used for both HTTP and SOCKS5 proxies
+    pub username: String,
+    // Saved in plain text in launcher.json, which the game and anything else running as the user can read
+    // MINELAUNCH_PROXY_PASSWORD is used instead when it's set
+    pub password: String,
+    // Hosts that are connected to directly, ie "localhost", or ".example.com" for it and all its subdomains
+    pub no_proxy: Vec<String>,
+    // Pass the proxy on to the game as -Dhttp.proxyHost and friends, or -DsocksProxyHost for SOCKS
+    pub java_properties: bool,
+}
+
+impl ProxyConfig {
+    pub fn is_enabled(&self) -> bool {
+        return !self.url.trim().is_empty();
+    }
+
+    // The proxy for reqwest, or None if none is set
+    pub fn build(&self) -> Result<Option<Proxy>, String> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        let mut url = self.parsed_url()?;
+        if !self.username.is_empty() {
+            url.set_username(&self.username).unwrap();
+            url.set_password(Some(&self.password())).unwrap();
+        }
+        // reqwest looks up SOCKS proxies on every request and goes direct if that fails, so look it up here instead
+        // This happens whenever the download client gets set up, which is tried again on the next download if it fails
+        if url.scheme().starts_with("socks5") {
+            let host = url.host_str().unwrap_or_default().to_string();
+            let address = (host.as_str(), url.port().unwrap_or(1080)).to_socket_addrs().ok().and_then(|mut a| a.next())
+                .ok_or_else(|| format!("Couldn't find the proxy {0}", host))?;
+            url.set_ip_host(address.ip()).unwrap();
+            url.set_port(Some(address.port())).unwrap();
+        }
+        // Make sure reqwest accepts it, since a custom proxy that it doesn't accept is silently skipped
+        Proxy::all(url.clone()).map_err(|e| format!("The proxy {0} can't be used: {1}", self.url, e))?;
+
+        let no_proxy = self.no_proxy.clone();
+        return Ok(Some(Proxy::custom(move |target| {
+            match target.host_str() {
+                Some(host) if bypasses(&no_proxy, host) => None,
+                _ => Some(url.clone()),
+            }
+        })));
+    }
+
+    // System properties that send the game's own connections, ie skins and servers, through the proxy
+    // Java only supports logging in to SOCKS proxies this way, and only the username is passed on,
+    // since anyone who can list processes can read the command line
+    // Proxies that need the password have to be set up for Java some other way, ie in the instance's JVM arguments
+    pub fn java_arguments(&self) -> Vec<String> {
+        let url = match self.parsed_url() {
+            Ok(url) if self.java_properties => url,
+            _ => return Vec::new(),
+        };
+        let host = url.host_str().unwrap_or_default();
+        let mut args = Vec::new();
+        if url.scheme().starts_with("socks5") {
+            args.push(format!("-DsocksProxyHost={0}", host));
+            args.push(format!("-DsocksProxyPort={0}", url.port().unwrap_or(1080)));
+            if !self.username.is_empty() {
+                args.push(format!("-Djava.net.socks.username={0}", self.username));
+            }
+        }
+        else {
+            let port = url.port_or_known_default().unwrap_or(80);
+            for scheme in ["http", "https"] {
+                args.push(format!("-D{0}.proxyHost={1}", scheme, host));
+                args.push(format!("-D{0}.proxyPort={1}", scheme, port));
+            }
+        }
+        if !self.no_proxy.is_empty() {
+            // Java wants "*.example.com" rather than ".example.com", and uses the same list for HTTPS
+            let hosts: Vec<String> = self.no_proxy.iter().map(|h| if h.starts_with('.') { format!("*{0}", h) } else { h.clone() }).collect();
+            let name = if url.scheme().starts_with("socks5") { "socksNonProxyHosts" } else { "http.nonProxyHosts" };
+            args.push(format!("-D{0}={1}", name, hosts.join("|")));
+        }
+        return args;
+    }
+
+    // The password from MINELAUNCH_PROXY_PASSWORD, or the saved one if it isn't set
+    pub fn password(&self) -> String {
+        return std::env::var(PASSWORD_VARIABLE).unwrap_or_else(|_| self.password.clone());
+    }
+
+    fn parsed_url(&self) -> Result<Url, String> {
+        let url = Url::parse(self.url.trim()).map_err(|e| format!("The proxy '{0}' isn't a valid URL: {1}", self.url, e))?;
+        if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+            return Err(format!("The proxy {0} isn't supported, only http, https, socks5 and socks5h proxies are", self.url));
+        }
+        if url.host_str().is_none() {
+            return Err(format!("The proxy {0} doesn't have a host", self.url));
+        }
+        return Ok(url);
+    }
+}
+
+// Whether a host is in the no proxy list, which works like curl's NO_PROXY
+// "example.com" and ".example.com" both match example.com and its subdomains, "*" matches everything
+fn bypasses(no_proxy: &[String], host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+    return no_proxy.iter().any(|entry| {
+        let entry = entry.trim();
+        if entry == "*" {
+            return true;
+        }
+        let entry = entry.trim_start_matches("*.").trim_start_matches('.').to_lowercase();
+        !entry.is_empty() && (host == entry || host.ends_with(&format!(".{0}", entry)))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_arguments_leave_out_the_password() {
+        let proxy = ProxyConfig {
+            url: String::from("socks5://127.0.0.1:1080"),
+            username: String::from("player"),
+            password: String::from("secret"),
+            no_proxy: vec![String::from(".example.com")],
+            java_properties: true,
+        };
+        let args = proxy.java_arguments();
+        assert_eq!(args, vec!["-DsocksProxyHost=127.0.0.1", "-DsocksProxyPort=1080", "-Djava.net.socks.username=player", "-DsocksNonProxyHosts=*.example.com"]);
+        assert!(!args.iter().any(|a| a.contains("secret")));
+    }
+}
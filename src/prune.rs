@@ -0,0 +1,362 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::config::LauncherConfig;
+use crate::instance::Instance;
+use crate::minecraft::installed_versions;
+use crate::spec::{AssetIndex, Library, VersionSpec};
+use crate::util::format_size;
+
+// What pruning removed, or would remove in a dry run
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub versions: Vec<String>,
+    // Paths relative to the launcher directory, ie "libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"
+    pub files: Vec<String>,
+    // Bytes freed, not counting files that stay around through another hardlink
+    pub reclaimed: u64,
+    // Anything that was skipped to be on the safe side
+    pub warnings: Vec<String>,
+}
+
+impl PruneReport {
+    // ie "Would remove 2 versions and 1536 files, reclaiming 734.2 MB"
+    pub fn summary(&self) -> String {
+        let action = if self.dry_run { "Would remove" } else { "Removed" };
+        return format!("{0} {1} versions and {2} files, reclaiming {3}", action, self.versions.len(), self.files.len(), format_size(self.reclaimed));
+    }
+}
+
+const SKIPPED_VERSIONS: &str = "Skipped removing versions since some specs couldn't be read";
+
+// Versions that prune and uninstall have to leave alone, with the reason why
+pub fn protected_versions(minecraft_path: &str) -> HashMap<String, String> {
+    let mut protected = HashMap::new();
+    for id in LauncherConfig::load(minecraft_path).pinned_versions {
+        protected.insert(id, String::from("pinned"));
+    }
+    for name in Instance::list(minecraft_path) {
+        let settings = Instance::load_settings(&format!("{0}/instances/{1}", minecraft_path, name)).unwrap_or_default();
+        if let Some(id) = settings.last_version {
+            protected.entry(id).or_insert(format!("used by instance {0}", name));
+        }
+    }
+    return protected;
+}
+
+// Instances that have been played but don't say which version they use, since they were last launched before it was recorded
+// Minecraft writes options.txt on the first launch, so instances that were never launched don't count
+fn unrecorded_instances(minecraft_path: &str) -> Vec<String> {
+    let mut unrecorded = Vec::new();
+    for name in Instance::list(minecraft_path) {
+        let instance_path = format!("{0}/instances/{1}", minecraft_path, name);
+        let settings = Instance::load_settings(&instance_path).unwrap_or_default();
+        if settings.last_version.is_none() && Path::new(&format!("{0}/options.txt", instance_path)).exists() {
+            unrecorded.push(name);
+        }
+    }
+    return unrecorded;
+}
+
+fn unrecorded_warning(name: &str) -> String {
+    return format!("Instance {0} hasn't been launched since versions started being recorded, so the version it uses isn't protected", name);
+}
+
+// Removes the libraries, asset indexes, asset objects and logging configs that no installed version uses
+// Also removes versions that aren't pinned or used by an instance if remove_versions is set
+// Nothing gets deleted in a dry run, but the report still says what would be
+pub fn prune_unused(minecraft_path: &str, remove_versions: bool, dry_run: bool) -> PruneReport {
+    let installed = installed_versions(minecraft_path);
+    let mut kept: Vec<String> = installed.clone();
+    if remove_versions {
+        let protected = protected_versions(minecraft_path);
+        kept = installed.iter().filter(|id| protected.contains_key(*id)).cloned().collect();
+    }
+
+    let mut report = plan(minecraft_path, &installed, kept);
+    report.dry_run = dry_run;
+    if remove_versions {
+        report.warnings.extend(unrecorded_instances(minecraft_path).iter().map(|name| unrecorded_warning(name)));
+    }
+    measure(minecraft_path, &mut report);
+    if !dry_run {
+        remove(minecraft_path, &mut report);
+    }
+    return report;
+}
+
+// Removes a version, along with the libraries and assets nothing else uses if remove_orphaned is set
+// Refuses to remove versions that are pinned, used by an instance, or that another installed version is built on
+pub fn uninstall_version(minecraft_path: &str, id: &str, remove_orphaned: bool) -> Result<PruneReport, String> {
+    if let Some(reason) = protected_versions(minecraft_path).get(id) {
+        return Err(format!("Minecraft {0} can't be uninstalled since it's {1}", id, reason));
+    }
+    let installed = installed_versions(minecraft_path);
+    if !installed.iter().any(|i| i == id) {
+        return Err(format!("Minecraft {0} isn't installed", id));
+    }
+    let kept: Vec<String> = installed.iter().filter(|i| *i != id).cloned().collect();
+    let mut report = plan(minecraft_path, &installed, kept);
+    if report.warnings.iter().any(|w| w == SKIPPED_VERSIONS) {
+        return Err(format!("Minecraft {0} can't be uninstalled since some specs couldn't be read:\n{1}", id, report.warnings.join("\n")));
+    }
+    if !report.versions.iter().any(|i| i == id) {
+        return Err(format!("Minecraft {0} can't be uninstalled since another installed version is built on it", id));
+    }
+
+    if !remove_orphaned {
+        report.files.clear();
+    }
+    measure(minecraft_path, &mut report);
+    remove(minecraft_path, &mut report);
+    report.warnings.extend(unrecorded_instances(minecraft_path).iter().map(|name| unrecorded_warning(name)));
+    return Ok(report);
+}
+
+// Space used by a version's own directory, and the space uninstalling it along with its orphaned data would free
+pub fn version_disk_usage(minecraft_path: &str, id: &str) -> (u64, u64) {
+    let version_size = WalkDir::new(format!("{0}/versions/{1}", minecraft_path, id)).into_iter()
+        .filter_map(|e| e.ok()).filter_map(|e| e.metadata().ok()).filter(|m| m.is_file()).map(|m| m.len()).sum();
+
+    let installed = installed_versions(minecraft_path);
+    let kept: Vec<String> = installed.iter().filter(|i| *i != id).cloned().collect();
+    let mut report = plan(minecraft_path, &installed, kept);
+    measure(minecraft_path, &mut report);
+    return (version_size, report.reclaimed);
+}
+
+// Works out which versions and files aren't needed when only the kept versions stay installed
+fn plan(minecraft_path: &str, installed: &[String], mut kept: Vec<String>) -> PruneReport {
+    let mut report = PruneReport {
+        dry_run: true,
+        ..PruneReport::default()
+    };
+
+    // Modded specs are built on top of other versions, which have to stay too
+    // The parent is read from the raw JSON, so that it's kept even if the rest of the spec can't be understood
+    let mut specs: Vec<VersionSpec> = Vec::new();
+    let mut unreadable = false;
+    let mut i = 0;
+    while i < kept.len() {
+        match read_spec_json(minecraft_path, &kept[i]) {
+            Ok(spec_json) => {
+                if let Some(parent) = spec_json.get("inheritsFrom").and_then(|p| p.as_str()) {
+                    if !kept.iter().any(|k| k == parent) {
+                        kept.push(parent.to_string());
+                    }
+                }
+                match serde_json::from_value(spec_json) {
+                    Ok(spec) => specs.push(spec),
+                    Err(e) => report.warnings.push(format!("Failed to parse the spec of Minecraft {0}: {1}", kept[i], e)),
+                }
+            },
+            Err(e) => {
+                report.warnings.push(e);
+                unreadable = true;
+            },
+        }
+        i += 1;
+    }
+    // There's no telling which version an unreadable spec is built on, so any of them could be needed
+    if unreadable {
+        report.warnings.push(String::from(SKIPPED_VERSIONS));
+    }
+    else {
+        for id in installed.iter() {
+            if !kept.contains(id) {
+                report.versions.push(id.clone());
+            }
+        }
+    }
+
+    // Collect everything the kept versions refer to, relative to the launcher directory
+    let mut referenced = HashSet::new();
+    let mut asset_ids = HashSet::new();
+    for spec in specs.iter() {
+        for library in spec.libraries.iter() {
+            for path in library_paths(library) {
+                referenced.insert(format!("libraries/{0}", path));
+            }
+        }
+        if let Some(logging) = spec.logging.as_ref().and_then(|l| l.client.as_ref()) {
+            referenced.insert(format!("assets/log_configs/{0}", logging.file.id));
+        }
+        let assets = match &spec.assets {
+            Some(assets) => assets,
+            None => continue,
+        };
+        if asset_ids.insert(assets.clone()) {
+            referenced.insert(format!("assets/indexes/{0}.json", assets));
+            match read_asset_index(minecraft_path, assets) {
+                Ok(index) => {
+                    for (name, object) in index.objects.iter() {
+                        referenced.insert(format!("assets/objects/{0}/{1}", &object.hash[..2], object.hash));
+                        referenced.insert(format!("assets/virtual/{0}/{1}", assets, name));
+                    }
+                },
+                Err(e) => report.warnings.push(e),
+            }
+        }
+    }
+
+    // Unreadable specs and indexes could be using anything, so only remove files when everything could be read
+    let mut directories = Vec::new();
+    if report.warnings.is_empty() {
+        directories = vec!["libraries", "assets/indexes", "assets/objects", "assets/virtual", "assets/log_configs"];
+    }
+    else {
+        report.warnings.push(String::from("Skipped libraries and assets since some specs or asset indexes couldn't be read"));
+    }
+    for directory in directories {
+        for entry in WalkDir::new(format!("{0}/{1}", minecraft_path, directory)).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(minecraft_path).unwrap().to_string_lossy().replace('\\', "/");
+            if !referenced.contains(&relative) {
+                report.files.push(relative);
+            }
+        }
+    }
+    report.files.sort();
+    return report;
+}
+
+// Fills in how much space removing everything in the report frees
+fn measure(minecraft_path: &str, report: &mut PruneReport) {
+    let mut removed_paths: Vec<String> = report.files.iter().map(|f| format!("{0}/{1}", minecraft_path, f)).collect();
+    for id in report.versions.iter() {
+        for entry in WalkDir::new(format!("{0}/versions/{1}", minecraft_path, id)).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_type().is_file() {
+                removed_paths.push(entry.path().to_string_lossy().into_owned());
+            }
+        }
+    }
+    report.reclaimed = reclaimed_size(&removed_paths);
+}
+
+fn remove(minecraft_path: &str, report: &mut PruneReport) {
+    report.dry_run = false;
+    for id in report.versions.iter() {
+        println!("Removing Minecraft {0}", id);
+        if let Err(e) = fs::remove_dir_all(format!("{0}/versions/{1}", minecraft_path, id)) {
+            report.warnings.push(format!("Failed to remove Minecraft {0}: {1}", id, e));
+        }
+    }
+    for file in report.files.iter() {
+        if let Err(e) = fs::remove_file(format!("{0}/{1}", minecraft_path, file)) {
+            report.warnings.push(format!("Failed to remove {0}: {1}", file, e));
+        }
+    }
+    for directory in ["libraries", "assets/objects", "assets/virtual"] {
+        remove_empty_dirs(&format!("{0}/{1}", minecraft_path, directory));
+    }
+}
+
+fn read_spec_json(minecraft_path: &str, id: &str) -> Result<Value, String> {
+    let spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, id);
+    let spec_json = fs::read_to_string(&spec_path).map_err(|e| format!("Failed to read {0}: {1}", spec_path, e))?;
+    return serde_json::from_str(&spec_json).map_err(|e| format!("Failed to parse {0}: {1}", spec_path, e));
+}
+
+fn read_asset_index(minecraft_path: &str, id: &str) -> Result<AssetIndex, String> {
+    let index_path = format!("{0}/assets/indexes/{1}.json", minecraft_path, id);
+    let index_json = fs::read_to_string(&index_path).map_err(|e| format!("Failed to read {0}: {1}", index_path, e))?;
+    return serde_json::from_str(&index_json).map_err(|e| format!("Failed to parse {0}: {1}", index_path, e));
+}
+
+// Every path under "libraries/" a library could be at, for any platform
+// Modded specs only give a Maven name, so the path that name maps to is included too
+fn library_paths(library: &Library) -> Vec<String> {
+    let mut paths = Vec::new();
+    if let Some(path) = library.downloads.artifact.as_ref().and_then(|a| a.path.clone()) {
+        paths.push(path);
+    }
+    if let Some(classifiers) = &library.downloads.classifiers {
+        paths.extend(classifiers.values().filter_map(|c| c.path.clone()));
+    }
+    if let Some(path) = maven_path(&library.name) {
+        paths.push(path);
+    }
+    return paths;
+}
+
+// ie "net.fabricmc:fabric-loader:0.14.9" to "net/fabricmc/fabric-loader/0.14.9/fabric-loader-0.14.9.jar"
+fn maven_path(name: &str) -> Option<String> {
+    let (name, extension) = name.split_once('@').unwrap_or((name, "jar"));
+    let parts: Vec<&str> = name.split(':').collect();
+    let (group, artifact, version) = (parts.first()?, parts.get(1)?, parts.get(2)?);
+    let classifier = parts.get(3).map_or(String::new(), |c| format!("-{0}", c));
+    return Some(format!("{0}/{1}/{2}/{1}-{2}{3}.{4}", group.replace('.', "/"), artifact, version, classifier, extension));
+}
+
+// Hardlinked files only free up space once every link to them is gone
+#[cfg(unix)]
+fn reclaimed_size(paths: &[String]) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    let mut inodes: HashMap<(u64, u64), (u64, u64, u64)> = HashMap::new();
+    for path in paths {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            let inode = inodes.entry((metadata.dev(), metadata.ino())).or_insert((metadata.len(), metadata.nlink(), 0));
+            inode.2 += 1;
+        }
+    }
+    return inodes.values().filter(|(_, links, removed)| removed >= links).map(|(size, _, _)| size).sum();
+}
+
+#[cfg(not(unix))]
+fn reclaimed_size(paths: &[String]) -> u64 {
+    return paths.iter().filter_map(|path| fs::metadata(path).ok()).map(|m| m.len()).sum();
+}
+
+fn remove_empty_dirs(root: &str) {
+    for entry in WalkDir::new(root).min_depth(1).contents_first(true).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            // Fails for directories that still have something in them, which is what's wanted
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_spec(minecraft_path: &str, id: &str, spec_json: &str) {
+        let version_path = format!("{0}/versions/{1}", minecraft_path, id);
+        fs::create_dir_all(&version_path).unwrap();
+        fs::write(format!("{0}/{1}.json", version_path, id), spec_json).unwrap();
+    }
+
+    #[test]
+    fn plan_keeps_parents_of_modded_versions() {
+        let directory = tempfile::tempdir().unwrap();
+        let minecraft_path = directory.path().to_str().unwrap();
+        write_spec(minecraft_path, "1.19.2", r#"{"id": "1.19.2", "libraries": [], "mainClass": "net.minecraft.client.main.Main", "type": "release"}"#);
+        write_spec(minecraft_path, "1.18.2", r#"{"id": "1.18.2", "libraries": [], "mainClass": "net.minecraft.client.main.Main", "type": "release"}"#);
+        // Missing the main class, so only the raw JSON can be read
+        write_spec(minecraft_path, "fabric-loader-1.19.2", r#"{"id": "fabric-loader-1.19.2", "inheritsFrom": "1.19.2", "type": "release"}"#);
+
+        let installed = installed_versions(minecraft_path);
+        let report = plan(minecraft_path, &installed, vec![String::from("fabric-loader-1.19.2")]);
+        assert_eq!(report.versions, vec![String::from("1.18.2")]);
+        assert!(report.files.is_empty());
+    }
+
+    #[test]
+    fn plan_keeps_versions_when_a_spec_is_unreadable() {
+        let directory = tempfile::tempdir().unwrap();
+        let minecraft_path = directory.path().to_str().unwrap();
+        write_spec(minecraft_path, "1.19.2", r#"{"id": "1.19.2", "libraries": [], "mainClass": "net.minecraft.client.main.Main", "type": "release"}"#);
+        write_spec(minecraft_path, "broken", "{");
+
+        let installed = installed_versions(minecraft_path);
+        let report = plan(minecraft_path, &installed, vec![String::from("broken")]);
+        assert!(report.versions.is_empty());
+        assert!(!report.warnings.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use futures::stream::StreamExt;
+use walkdir::WalkDir;
+
+use crate::hash_cache::HashCache;
+use crate::instance::Instance;
+use crate::minecraft::{RequiredFile, asset_copies, download_minecraft_version, java_runtime_dir, required_assets, required_libraries, required_logging_config};
+use crate::pool::map_parallel;
+use crate::spec::{AssetIndex, MinecraftVersion, VersionSpec};
+use crate::util::{file_sha1, get_os};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    Missing,
+    // Exists but doesn't match its hash, or doesn't work in the case of Java
+    Corrupt,
+    // Left in a directory the launcher manages, but not needed by anything
+    Extraneous,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Problem::Missing => "missing",
+            Problem::Corrupt => "corrupt",
+            Problem::Extraneous => "extraneous",
+        };
+        write!(f, "{0}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub problem: Problem,
+    // ie "Library com.mojang:brigadier:1.0.18"
+    pub id: String,
+    pub path: String,
+}
+
+// Everything wrong with an installed version
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub version: String,
+    // How many files were checked, including the Java runtime as one
+    pub checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl VerifyReport {
+    fn new(version: &str) -> VerifyReport {
+        VerifyReport {
+            version: version.to_string(),
+            checked: 0,
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        return self.findings.is_empty();
+    }
+
+    pub fn count(&self, problem: Problem) -> usize {
+        return self.findings.iter().filter(|f| f.problem == problem).count();
+    }
+
+    // ie "Checked 3781 files for Minecraft 1.19.2: 2 missing, 1 corrupt, 0 extraneous"
+    pub fn summary(&self) -> String {
+        return format!("Checked {0} files for Minecraft {1}: {2} missing, {3} corrupt, {4} extraneous",
+                       self.checked, self.version, self.count(Problem::Missing), self.count(Problem::Corrupt), self.count(Problem::Extraneous));
+    }
+
+    // A line for each finding, ie "corrupt: Library com.mojang:brigadier:1.0.18 ({path})"
+    pub fn lines(&self) -> Vec<String> {
+        return self.findings.iter().map(|f| format!("{0}: {1} ({2})", f.problem, f.id, f.path)).collect();
+    }
+
+    fn push(&mut self, problem: Problem, id: &str, path: &str) {
+        self.findings.push(Finding {
+            problem: problem,
+            id: id.to_string(),
+            path: path.to_string(),
+        });
+    }
+}
+
+// Checks everything an installed version needs without downloading anything
+// Deep verify hashes every file instead of trusting the hash cache for files that look unchanged
+pub async fn verify_version(minecraft_path: String, version: MinecraftVersion, instance: Option<Instance>, deep_verify: bool) -> Result<VerifyReport, String> {
+    let hashes = Arc::new(HashCache::open(&minecraft_path, deep_verify));
+    let mut report = VerifyReport::new(&version.id);
+
+    // Nothing else can be checked without the spec
+    let spec_path = format!("{0}/versions/{1}/{1}.json", minecraft_path, version.id);
+    report.checked += 1;
+    match file_sha1(Path::new(&spec_path)) {
+        Some(sha1) if sha1 == version.sha1 => (),
+        Some(_) => report.push(Problem::Corrupt, "Version spec", &spec_path),
+        None => {
+            report.push(Problem::Missing, "Version spec", &spec_path);
+            hashes.save();
+            return Ok(report);
+        },
+    }
+    // A spec that's only outdated can still be checked against
+    let spec: VersionSpec = match fs::read_to_string(&spec_path).ok().and_then(|spec_json| serde_json::from_str(&spec_json).ok()) {
+        Some(spec) => spec,
+        None => {
+            if report.count(Problem::Corrupt) == 0 {
+                report.push(Problem::Corrupt, "Version spec", &spec_path);
+            }
+            hashes.save();
+            return Ok(report);
+        },
+    };
+
+    // Specs that build on another version can leave out the jar and the assets
+    let mut files = Vec::new();
+    if let Ok(client) = spec.client_download() {
+        files.push(RequiredFile {
+            id: format!("Minecraft {0} jar", version.id),
+            path: format!("{0}/versions/{1}/{1}.jar", minecraft_path, version.id),
+            url: client.url.clone(),
+            sha1: client.sha1.clone(),
+            size: client.size,
+        });
+    }
+    files.extend(required_libraries(&minecraft_path, &spec)?);
+    files.extend(required_logging_config(&minecraft_path, &spec));
+
+    // The objects can only be checked if the index is fine
+    let mut asset_index = None;
+    if let (Ok(assets), Ok(index_download)) = (spec.assets_id(), spec.asset_index_download()) {
+        let index_path = format!("{0}/assets/indexes/{1}.json", minecraft_path, assets);
+        report.checked += 1;
+        if hashes.check_file(Path::new(&index_path), &index_download.sha1, index_download.size) {
+            let index_json = fs::read_to_string(&index_path).map_err(|e| format!("Failed to read {0}: {1}", index_path, e))?;
+            let index: AssetIndex = serde_json::from_str(&index_json).map_err(|e| format!("Failed to parse {0}: {1}", index_path, e))?;
+            files.extend(required_assets(&minecraft_path, &index));
+            files.extend(asset_copies(&minecraft_path, assets, &index).into_iter().map(|(_, file)| file));
+            asset_index = Some((assets, index));
+        }
+        else {
+            let problem = if Path::new(&index_path).exists() { Problem::Corrupt } else { Problem::Missing };
+            report.push(problem, &format!("Asset Index {0}", assets), &index_path);
+        }
+    }
+
+    report.checked += files.len();
+    let verify_hashes = hashes.clone();
+    let mut verified = map_parallel(files, move |file| {
+        let valid = verify_hashes.check_file(Path::new(&file.path), &file.sha1, file.size);
+        Ok((file, valid))
+    });
+    while let Some(result) = verified.next().await {
+        let (file, valid) = result?;
+        if !valid {
+            let problem = if Path::new(&file.path).exists() { Problem::Corrupt } else { Problem::Missing };
+            report.push(problem, &file.id, &file.path);
+        }
+    }
+    hashes.save();
+
+    // Virtual assets are only ever written by the launcher, so anything not in the index doesn't belong there
+    if let Some((assets, index)) = &asset_index {
+        if index.virtual_assets == Some(true) {
+            let virtual_dir = format!("{0}/assets/virtual/{1}", minecraft_path, assets);
+            let expected: HashSet<&str> = index.objects.keys().map(|name| name.as_str()).collect();
+            for entry in WalkDir::new(&virtual_dir).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+                let name = entry.path().strip_prefix(&virtual_dir).unwrap().to_string_lossy().replace('\\', "/");
+                if !expected.contains(name.as_str()) {
+                    report.push(Problem::Extraneous, &format!("Virtual asset {0}", name), &entry.path().to_string_lossy());
+                }
+            }
+        }
+    }
+
+    let java_version = spec.java_version.as_ref().map_or(8, |v| v.major_version);
+    report.checked += 1;
+    check_java_runtime(&minecraft_path, java_version, &mut report);
+
+    // The instance's own logging config can't be downloaded again, but it's still worth knowing about
+    if let Some(config_path) = instance.as_ref().and_then(|i| i.settings.logging_config.as_ref()) {
+        report.checked += 1;
+        if !Path::new(config_path).exists() {
+            report.push(Problem::Missing, "Instance logging config", config_path);
+        }
+    }
+
+    report.findings.sort_by(|a, b| a.path.cmp(&b.path));
+    return Ok(report);
+}
+
+// There's no manifest to check the runtime against, so make sure Java actually runs instead
+fn check_java_runtime(minecraft_path: &str, java_version: u8, report: &mut VerifyReport) {
+    let runtime_dir = java_runtime_dir(minecraft_path, java_version);
+    let id = format!("Java {0} runtime", java_version);
+    if !Path::new(&runtime_dir).exists() {
+        report.push(Problem::Missing, &id, &runtime_dir);
+        return;
+    }
+
+    let java_path = format!("{0}bin/{1}", runtime_dir, if get_os() == "windows" { "java.exe" } else { "java" });
+    let runs = Command::new(&java_path).arg("-version")
+        .stdout(Stdio::null()).stderr(Stdio::null())
+        .status().is_ok_and(|status| status.success());
+    if !runs {
+        report.push(Problem::Corrupt, &id, &runtime_dir);
+    }
+}
+
+// Verifies a version, fixes whatever can be fixed, then verifies it again to show what's left
+// Extraneous files and broken Java runtimes are removed, then everything missing or corrupt is downloaded again
+pub async fn repair_version(minecraft_path: String, version: MinecraftVersion, instance: Option<Instance>, deep_verify: bool) -> Result<(VerifyReport, VerifyReport), String> {
+    if version.is_custom() {
+        return Err(format!("Minecraft {0} is a custom version, so it can't be repaired", version.id));
+    }
+    let before = verify_version(minecraft_path.clone(), version.clone(), instance.clone(), deep_verify).await?;
+    if before.is_ok() {
+        return Ok((before.clone(), before));
+    }
+
+    for finding in before.findings.iter() {
+        if finding.problem == Problem::Extraneous {
+            println!("Removing {0}", finding.id);
+            fs::remove_file(&finding.path).map_err(|e| format!("Failed to remove {0}: {1}", finding.path, e))?;
+        }
+        // The only directory that gets checked is the Java runtime, which has to be installed from scratch
+        else if finding.problem == Problem::Corrupt && Path::new(&finding.path).is_dir() {
+            println!("Removing broken {0}", finding.id);
+            fs::remove_dir_all(&finding.path).map_err(|e| format!("Failed to remove {0}: {1}", finding.path, e))?;
+        }
+    }
+
+    // Verifying just refreshed the hash cache, so the download only has to fetch what's broken
+    download_minecraft_version(minecraft_path.clone(), version.clone(), false).await?;
+    let after = verify_version(minecraft_path, version, instance, false).await?;
+    return Ok((before, after));
+}
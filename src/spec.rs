@@ -0,0 +1,589 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::util::get_os_minecraft;
+
+// The newest spec format the launcher understands, compared against minimumLauncherVersion
+pub const SUPPORTED_LAUNCHER_VERSION: u32 = 21;
+
+// Features that rules can check for, which are all treated as disabled
+const KNOWN_FEATURES: [&str; 6] = ["is_demo_user", "has_custom_resolution", "has_quick_plays_support",
+                                   "is_quick_play_singleplayer", "is_quick_play_multiplayer", "is_quick_play_realms"];
+
+// Types for the JSON files Mojang publishes
+// Fields that aren't known get kept in `extra` so that files can be written back out without losing anything
+
+// Types for version list JSON (version_manifest_v2.json)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MinecraftLatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MinecraftVersion {
+    pub id: String,
+    #[serde(rename="type")]
+    pub version_type: String,
+    pub url: String,
+    pub time: String,
+    #[serde(rename="releaseTime")]
+    pub release_time: String,
+    // Hash of the version spec
+    pub sha1: String,
+    // 0 for versions that predate the safety features, 1 for versions with them
+    #[serde(rename="complianceLevel")]
+    pub compliance_level: u32,
+}
+
+impl MinecraftVersion {
+    // Custom versions, like modded profiles, only exist locally, so there's nowhere to download them from
+    pub fn is_custom(&self) -> bool {
+        return self.url.is_empty();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MinecraftVersionList {
+    pub latest: MinecraftLatestVersions,
+    pub versions: Vec<MinecraftVersion>,
+}
+
+// Types for version spec JSON (versions/{id}/{id}.json)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum SingleOrVec<T> {
+    Single(T),
+    Vector(Vec<T>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DynamicArgument {
+    pub rules: Vec<Rule>,
+    pub value: SingleOrVec<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Argument {
+    Static(String),
+    Dynamic(DynamicArgument),
+    // Anything else, which gets skipped and reported as incompatible
+    Unknown(Value),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionArguments {
+    pub game: Vec<Argument>,
+    pub jvm: Vec<Argument>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionAssets {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    #[serde(rename="totalSize")]
+    pub total_size: u64,
+    pub url: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Download {
+    // Only library downloads have a path
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub path: Option<String>,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionDownloads {
+    pub client: Download,
+    // Deobfuscation mappings don't exist for versions before 1.14.4
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub client_mappings: Option<Download>,
+    // Server doesn't exist for versions before 1.2.5
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub server: Option<Download>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub server_mappings: Option<Download>,
+    // Only some versions between 1.2.5 and 1.8 had a separate Windows server
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub windows_server: Option<Download>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LibraryDownloads {
+    // Apparently in older versions some libraries might not have an artifact
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub artifact: Option<Download>,
+    // This doesn't have a fully specified layout because a classifier can be called anything
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub classifiers: Option<BTreeMap<String, Download>>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryNatives {
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub linux: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub osx: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub windows: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryExtractOptions {
+    pub exclude: Vec<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleOS {
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub name: Option<String>,
+    // A regex matched against the OS version, ie "^10\\."
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rule {
+    pub action: String,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub os: Option<RuleOS>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub features: Option<BTreeMap<String, bool>>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Library {
+    // Modded specs can leave this out and give a Maven repository instead
+    #[serde(default, skip_serializing_if="LibraryDownloads::is_empty")]
+    pub downloads: LibraryDownloads,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub extract: Option<LibraryExtractOptions>,
+    pub name: String,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub natives: Option<LibraryNatives>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+    // Maven repository to download from, used by modded specs instead of downloads
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JavaVersion {
+    // Name of the runtime in Mojang's Java manifest, ie "java-runtime-gamma"
+    pub component: String,
+    #[serde(rename="majorVersion")]
+    pub major_version: u8,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoggingConfig {
+    // Contains ${path}, which gets replaced with the path to the config file
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename="type")]
+    pub config_type: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionLogging {
+    // Only the client is ever launched, so the server logging config doesn't matter
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub client: Option<LoggingConfig>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub server: Option<LoggingConfig>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionSpec {
+    // Versions before 1.13 use minecraftArguments instead
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub arguments: Option<VersionArguments>,
+    // Specs that build on another version, ie modded ones, can leave out anything the other version has
+    #[serde(rename="assetIndex", skip_serializing_if="Option::is_none")]
+    pub asset_index: Option<VersionAssets>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(rename="complianceLevel", skip_serializing_if="Option::is_none")]
+    pub compliance_level: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub downloads: Option<VersionDownloads>,
+    pub id: String,
+    // Modded specs build on top of another version's spec
+    #[serde(rename="inheritsFrom", skip_serializing_if="Option::is_none")]
+    pub inherits_from: Option<String>,
+    // Java versions aren't given for versions before 1.6.1
+    #[serde(rename="javaVersion", skip_serializing_if="Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+    pub libraries: Vec<Library>,
+    // Logging configs don't exist for versions before 1.7
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub logging: Option<VersionLogging>,
+    #[serde(rename="mainClass")]
+    pub main_class: String,
+    #[serde(rename="minecraftArguments", skip_serializing_if="Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(rename="minimumLauncherVersion", skip_serializing_if="Option::is_none")]
+    pub minimum_launcher_version: Option<u32>,
+    #[serde(rename="releaseTime", skip_serializing_if="Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub time: Option<String>,
+    #[serde(rename="type")]
+    pub version_type: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+// Types for asset index JSON (assets/indexes/{id}.json)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetObject {
+    pub hash: String,
+    pub size: u64,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetIndex {
+    pub objects: BTreeMap<String, AssetObject>,
+    #[serde(rename="virtual", skip_serializing_if="Option::is_none")]
+    pub virtual_assets: Option<bool>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub map_to_resources: Option<bool>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl LibraryDownloads {
+    pub fn is_empty(&self) -> bool {
+        return self.artifact.is_none() && self.classifiers.is_none() && self.extra.is_empty();
+    }
+}
+
+// Parts of a version spec that the launcher doesn't understand
+#[derive(Debug, Clone, Default)]
+pub struct CompatibilityReport {
+    // Problems that would make the launch fail or run differently from the official launcher
+    pub errors: Vec<String>,
+    // Things that are ignored but probably don't matter
+    pub warnings: Vec<String>,
+}
+
+impl CompatibilityReport {
+    pub fn is_compatible(&self) -> bool {
+        return self.errors.is_empty();
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        for error in self.errors.iter() {
+            lines.push(format!("Error: {0}", error));
+        }
+        for warning in self.warnings.iter() {
+            lines.push(format!("Warning: {0}", warning));
+        }
+        return lines.join("\n");
+    }
+
+    fn check_rules(&mut self, rules: &[Rule], context: &str) {
+        for rule in rules.iter() {
+            if rule.action != "allow" && rule.action != "disallow" {
+                self.errors.push(format!("{0} has a rule with the unknown action '{1}'", context, rule.action));
+            }
+            if let Some(os) = &rule.os {
+                if os.version.is_some() {
+                    self.warnings.push(format!("{0} has a rule on the OS version, which is ignored", context));
+                }
+                if !os.extra.is_empty() {
+                    self.errors.push(format!("{0} has a rule on unknown OS properties {1}", context, keys(&os.extra)));
+                }
+            }
+            if let Some(features) = &rule.features {
+                for feature in features.keys() {
+                    if !KNOWN_FEATURES.contains(&feature.as_str()) {
+                        self.warnings.push(format!("{0} has a rule on the unknown feature '{1}', which is treated as disabled", context, feature));
+                    }
+                }
+            }
+            if !rule.extra.is_empty() {
+                self.errors.push(format!("{0} has a rule on unknown properties {1}", context, keys(&rule.extra)));
+            }
+        }
+    }
+
+    fn check_arguments(&mut self, arguments: &[Argument], kind: &str) {
+        for argument in arguments.iter() {
+            match argument {
+                Argument::Static(_) => {},
+                Argument::Dynamic(dynamic_argument) => {
+                    self.check_rules(&dynamic_argument.rules, &format!("A {0} argument", kind));
+                },
+                Argument::Unknown(value) => {
+                    self.errors.push(format!("The {0} argument {1} has an unknown layout", kind, value));
+                },
+            }
+        }
+    }
+}
+
+fn keys(map: &BTreeMap<String, Value>) -> String {
+    return map.keys().map(|k| k.as_str()).collect::<Vec<&str>>().join(", ");
+}
+
+impl VersionSpec {
+    // Looks for anything in the spec that the launcher can't handle
+    pub fn compatibility_report(&self) -> CompatibilityReport {
+        let mut report = CompatibilityReport::default();
+        if let Some(minimum_launcher_version) = self.minimum_launcher_version.filter(|v| *v > SUPPORTED_LAUNCHER_VERSION) {
+            report.errors.push(format!("{0} needs launcher version {1}, but only up to {2} is supported",
+                                       self.id, minimum_launcher_version, SUPPORTED_LAUNCHER_VERSION));
+        }
+        if let Some(parent) = &self.inherits_from {
+            report.errors.push(format!("{0} builds on {1}, but specs that inherit from another version aren't supported", self.id, parent));
+        }
+        else {
+            // Only worth mentioning when they aren't missing because of the above
+            for error in [self.client_download().err(), self.asset_index_download().err(), self.assets_id().err()].into_iter().flatten() {
+                report.errors.push(error);
+            }
+        }
+
+        match (&self.arguments, &self.minecraft_arguments) {
+            (Some(arguments), _) => {
+                report.check_arguments(&arguments.jvm, "JVM");
+                report.check_arguments(&arguments.game, "game");
+            },
+            (None, Some(_)) => {},
+            (None, None) => report.errors.push(format!("{0} doesn't give any game arguments", self.id)),
+        }
+
+        for library in self.libraries.iter() {
+            let context = format!("Library {0}", library.name);
+            if let Some(rules) = &library.rules {
+                report.check_rules(rules, &context);
+            }
+            if library.downloads.is_empty() {
+                match &library.url {
+                    Some(url) => report.errors.push(format!("{0} comes from the Maven repository {1}, which isn't supported", context, url)),
+                    None => report.errors.push(format!("{0} has nothing to download", context)),
+                }
+            }
+            if library.downloads.artifact.as_ref().is_some_and(|a| a.path.is_none()) {
+                report.errors.push(format!("{0} doesn't say where its jar goes", context));
+            }
+
+            // Natives are picked by a classifier for the current OS
+            let classifier_name = library.natives.as_ref().and_then(|n| {
+                match get_os_minecraft() {
+                    "windows" => n.windows.as_ref(),
+                    "osx" => n.osx.as_ref(),
+                    "linux" => n.linux.as_ref(),
+                    _ => None,
+                }
+            });
+            if let Some(classifier_name) = classifier_name {
+                if classifier_name.contains("${") {
+                    report.errors.push(format!("{0} has the native classifier '{1}', but variables in classifiers aren't supported", context, classifier_name));
+                }
+                else if !library.downloads.classifiers.as_ref().is_some_and(|c| c.contains_key(classifier_name)) {
+                    report.errors.push(format!("{0} is missing its native classifier '{1}'", context, classifier_name));
+                }
+            }
+        }
+
+        if !self.extra.is_empty() {
+            report.warnings.push(format!("{0} has unknown properties {1}, which are ignored", self.id, keys(&self.extra)));
+        }
+        return report;
+    }
+
+    // Every URL the spec has the launcher download from, for checking them all before downloading anything
+    pub fn download_urls(&self) -> Vec<&str> {
+        let mut urls = Vec::new();
+        if let Some(downloads) = &self.downloads {
+            urls.push(downloads.client.url.as_str());
+            if let Some(client_mappings) = &downloads.client_mappings {
+                urls.push(&client_mappings.url);
+            }
+        }
+        if let Some(asset_index) = &self.asset_index {
+            urls.push(&asset_index.url);
+        }
+        for library in self.libraries.iter() {
+            if let Some(artifact) = &library.downloads.artifact {
+                urls.push(&artifact.url);
+            }
+            if let Some(classifiers) = &library.downloads.classifiers {
+                urls.extend(classifiers.values().map(|c| c.url.as_str()));
+            }
+        }
+        if let Some(logging) = self.logging.as_ref().and_then(|l| l.client.as_ref()) {
+            urls.push(&logging.file.url);
+        }
+        return urls;
+    }
+
+    // The client jar, or why there isn't one
+    pub fn client_download(&self) -> Result<&Download, String> {
+        return self.downloads.as_ref().map(|d| &d.client).ok_or_else(|| format!("{0} doesn't have a client jar", self.id));
+    }
+
+    pub fn asset_index_download(&self) -> Result<&VersionAssets, String> {
+        return self.asset_index.as_ref().ok_or_else(|| format!("{0} doesn't have an asset index", self.id));
+    }
+
+    // Name of the asset index, ie "1.19", which assets get stored under
+    pub fn assets_id(&self) -> Result<&str, String> {
+        return self.assets.as_deref().ok_or_else(|| format!("{0} doesn't say which assets it uses", self.id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed down from 1.19.2's spec, with a few unknown properties added at different levels
+    const VANILLA_SPEC: &str = r#"{
+        "arguments": {
+            "game": [
+                "--username", "${auth_player_name}",
+                {"rules": [{"action": "allow", "features": {"is_demo_user": true}}], "value": "--demo"},
+                {"rules": [{"action": "allow", "features": {"has_custom_resolution": true}}], "value": ["--width", "${resolution_width}"]}
+            ],
+            "jvm": [
+                {"rules": [{"action": "allow", "os": {"name": "osx"}}], "value": ["-XstartOnFirstThread"]},
+                {"rules": [{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}], "value": "-Dos.version=10.0"},
+                "-cp", "${classpath}"
+            ],
+            "newArgumentKind": []
+        },
+        "assetIndex": {"id": "1.19", "sha1": "a1b2", "size": 385487, "totalSize": 556915331, "url": "https://piston-meta.mojang.com/v1/packages/a1b2/1.19.json"},
+        "assets": "1.19",
+        "complianceLevel": 1,
+        "downloads": {
+            "client": {"sha1": "c3d4", "size": 21589508, "url": "https://piston-data.mojang.com/v1/objects/c3d4/client.jar"},
+            "client_mappings": {"sha1": "e5f6", "size": 7453474, "url": "https://piston-data.mojang.com/v1/objects/e5f6/client.txt"},
+            "server": {"sha1": "a7b8", "size": 45581593, "url": "https://piston-data.mojang.com/v1/objects/a7b8/server.jar", "signature": "abc"}
+        },
+        "id": "1.19.2",
+        "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17},
+        "libraries": [
+            {
+                "downloads": {"artifact": {"path": "com/mojang/logging/1.0.0/logging-1.0.0.jar", "sha1": "f9a0", "size": 15343, "url": "https://libraries.minecraft.net/com/mojang/logging/1.0.0/logging-1.0.0.jar"}},
+                "name": "com.mojang:logging:1.0.0"
+            },
+            {
+                "downloads": {
+                    "artifact": {"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar", "sha1": "b1c2", "size": 724243, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"},
+                    "classifiers": {"natives-linux": {"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar", "sha1": "d3e4", "size": 110704, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar"}}
+                },
+                "extract": {"exclude": ["META-INF/"]},
+                "name": "org.lwjgl:lwjgl:3.3.1",
+                "natives": {"linux": "natives-linux"},
+                "rules": [{"action": "allow"}, {"action": "disallow", "os": {"name": "osx", "arch": "arm64"}}],
+                "checksums": ["d3e4"]
+            }
+        ],
+        "logging": {
+            "client": {
+                "argument": "-Dlog4j.configurationFile=${path}",
+                "file": {"id": "client-1.12.xml", "sha1": "bd65", "size": 888, "url": "https://piston-data.mojang.com/v1/objects/bd65/client-1.12.xml"},
+                "type": "log4j2-xml"
+            }
+        },
+        "mainClass": "net.minecraft.client.main.Main",
+        "minimumLauncherVersion": 21,
+        "releaseTime": "2022-08-05T11:57:05+00:00",
+        "time": "2022-08-05T11:57:05+00:00",
+        "type": "release",
+        "unknownTopLevel": {"nested": [1, 2, 3]}
+    }"#;
+
+    // A Fabric profile, which leaves out everything it gets from the version it builds on
+    const MODDED_SPEC: &str = r#"{
+        "id": "fabric-loader-0.14.9-1.19.2",
+        "inheritsFrom": "1.19.2",
+        "releaseTime": "2022-08-06T12:00:00+0000",
+        "time": "2022-08-06T12:00:00+0000",
+        "type": "release",
+        "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+        "arguments": {"game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]},
+        "libraries": [{"name": "net.fabricmc:fabric-loader:0.14.9", "url": "https://maven.fabricmc.net/"}]
+    }"#;
+
+    fn round_trip(spec_json: &str) -> VersionSpec {
+        let original: Value = serde_json::from_str(spec_json).unwrap();
+        let spec: VersionSpec = serde_json::from_str(spec_json).unwrap();
+        assert_eq!(serde_json::to_value(&spec).unwrap(), original);
+        return spec;
+    }
+
+    #[test]
+    fn vanilla_spec_round_trips() {
+        let spec = round_trip(VANILLA_SPEC);
+        assert_eq!(spec.minimum_launcher_version, Some(21));
+        assert_eq!(spec.assets_id(), Ok("1.19"));
+        assert_eq!(spec.client_download().unwrap().size, 21589508);
+        assert!(spec.extra.contains_key("unknownTopLevel"));
+        assert!(spec.arguments.as_ref().unwrap().extra.contains_key("newArgumentKind"));
+        assert!(spec.libraries[1].extra.contains_key("checksums"));
+    }
+
+    #[test]
+    fn modded_spec_round_trips() {
+        let spec = round_trip(MODDED_SPEC);
+        assert_eq!(spec.inherits_from.as_deref(), Some("1.19.2"));
+        assert!(spec.client_download().is_err());
+        assert!(spec.asset_index_download().is_err());
+        assert!(spec.minimum_launcher_version.is_none());
+        assert!(!spec.compatibility_report().is_compatible());
+    }
+}
@@ -1,36 +1,204 @@
 use std::path::Path;
 use sha1::Sha1;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fs::{self, File};
+use std::io::Read;
+use std::process::Command;
 
-pub fn check_file(file_path: &Path, sha1: &str, size: u64) -> bool {
-    // Check if the file actually exists first
-    if !file_path.exists() {
-        return false;
+use crate::net;
+
+pub fn sha1_hex(data: &[u8]) -> String {
+    return Sha1::from(data).hexdigest();
+}
+
+// SHA-1 of a file's contents, or None if it can't be read
+// Reads in chunks so that big files don't have to fit in memory
+pub fn file_sha1(file_path: &Path) -> Option<String> {
+    let mut file = File::open(file_path).ok()?;
+    let mut hasher = Sha1::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
     }
+    return Some(hasher.digest().to_string());
+}
+
+// Writes a file through a temporary file next to it, so that it's never left half written
+pub fn write_atomic(file_path: &Path, contents: &[u8]) -> Result<(), String> {
+    let temp_path = format!("{0}.tmp", file_path.display());
+    fs::write(&temp_path, contents).map_err(|e| format!("Failed to write {0}: {1}", temp_path, e))?;
+    return fs::rename(&temp_path, file_path).map_err(|e| format!("Failed to replace {0}: {1}", file_path.display(), e));
+}
+
+// Downloads a file that has to match the given SHA-1 and writes it in place, returning its ID
+pub async fn download_verified(file_path: String, url: String, sha1: String, id: String) -> Result<String, String> {
+    let bytes = net::get_verified(&url, &sha1).await.map_err(|e| format!("{0}: {1}", id, e))?;
+    write_atomic(Path::new(&file_path), &bytes)?;
+    return Ok(id);
+}
 
-    // Check if the size matches
-    let mut file = File::open(file_path).unwrap();
-    if file.metadata().unwrap().len() != size {
-        return false;
+// Human readable size, ie "512.3 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{0} B", bytes);
     }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    return format!("{0:.1} {1}", size, UNITS[unit]);
+}
 
-    // Check if sha1 hash matches
-    let mut file_content = Vec::new();
-    file.read_to_end(&mut file_content).unwrap();
-    if Sha1::from(file_content).hexdigest() != sha1 {
-        return false;
+// Opens a file or folder with the default application for the platform
+pub fn open_path(path: &str) {
+    let result = match get_os() {
+        "windows" => Command::new("cmd").args(["/C", "start", "", path]).spawn(),
+        "macos" => Command::new("open").arg(path).spawn(),
+        _ => Command::new("xdg-open").arg(path).spawn(),
+    };
+    if let Err(e) = result {
+        println!("Failed to open {0}: {1}", path, e);
     }
+}
 
-    return true;
+// Total physical memory in megabytes, or None if it couldn't be found
+pub fn get_total_memory() -> Option<u64> {
+    match get_os() {
+        "linux" => {
+            // ie "MemTotal:       16303480 kB"
+            let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+            let total_line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+            let total_kb: u64 = total_line.split_whitespace().nth(1)?.parse().ok()?;
+            Some(total_kb / 1024)
+        },
+        "macos" => {
+            let output = Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+            let total_bytes: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+            Some(total_bytes / 1024 / 1024)
+        },
+        "windows" => {
+            let output = Command::new("powershell").args(["-NoProfile", "-Command", "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory"]).output().ok()?;
+            let total_bytes: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+            Some(total_bytes / 1024 / 1024)
+        },
+        _ => None,
+    }
 }
 
-pub async fn download_to_file(file_path: String, url: String, id: String) -> String {
-    let file_path = Path::new(&file_path);
-    let response = reqwest::get(url).await.unwrap();
-    let mut file = File::create(file_path).unwrap();
-    file.write_all(&response.bytes().await.unwrap()).unwrap();
-    id
+// Whether a backslash before this character escapes it, any other backslash is kept as is so Windows paths still work
+fn is_escapable(c: char) -> bool {
+    return c == '\\' || c == '"' || c == '\'' || c.is_whitespace();
+}
+
+// Splits a string into arguments on whitespace, keeping quoted sections together
+// Outside single quotes, a backslash escapes a following backslash, quote or whitespace
+pub fn split_args(args: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = args.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('"') if c == '\\' && chars.peek().is_some_and(|next| is_escapable(*next)) => current.push(chars.next().unwrap()),
+            Some(_) => current.push(c),
+            None if c == '\\' && chars.peek().is_some_and(|next| is_escapable(*next)) => {
+                current.push(chars.next().unwrap());
+                in_arg = true;
+            },
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            },
+            None if c.is_whitespace() => {
+                if in_arg {
+                    split.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            },
+            None => {
+                current.push(c);
+                in_arg = true;
+            },
+        }
+    }
+    if in_arg {
+        split.push(current);
+    }
+    return split;
+}
+
+// Joins arguments back into a string that split_args will split the same way
+pub fn join_args(args: &[String]) -> String {
+    let quoted: Vec<String> = args.iter().map(|a| {
+        let needs_quotes = a.is_empty() || a.contains(char::is_whitespace) || a.contains(['"', '\'']);
+        // Only backslashes that split_args would read as an escape need escaping themselves
+        // A backslash at the end is followed by a space or the closing quote
+        let mut escaped = String::new();
+        let mut chars = a.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '"' || (c == '\\' && chars.peek().is_none_or(|next| is_escapable(*next))) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        if needs_quotes {
+            format!("\"{0}\"", escaped)
+        }
+        else {
+            escaped
+        }
+    }).collect();
+    return quoted.join(" ");
+}
+
+// Quotes an argument for a POSIX shell, ie "it's" becomes 'it'\''s'
+pub fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c)) {
+        return arg.to_string();
+    }
+    return format!("'{0}'", arg.replace('\'', "'\\''"));
+}
+
+// Quotes an argument for a batch file, so that cmd leaves it alone and Java's argument parsing gets it back intact
+// Percent signs are doubled for cmd, quotes and the backslashes before them are escaped for Java
+// cmd still sees a quote inside an argument as the end of the quoted part, so ie & after one would be run as a command
+pub fn batch_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@+\\".contains(c)) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted += &"\\".repeat(backslashes * 2 + 1);
+                backslashes = 0;
+            },
+            _ => {
+                quoted += &"\\".repeat(backslashes);
+                backslashes = 0;
+            },
+        }
+        if c == '%' {
+            quoted.push('%');
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    // Backslashes before the closing quote would escape it otherwise
+    quoted += &"\\".repeat(backslashes * 2);
+    quoted.push('"');
+    return quoted;
 }
 
 pub fn get_os() -> &'static str {
@@ -87,3 +255,51 @@ pub fn get_os_minecraft() -> &'static str {
         os
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        return args.iter().map(|a| a.to_string()).collect();
+    }
+
+    #[test]
+    fn split_args_handles_quotes_and_escapes() {
+        assert_eq!(split_args("  -Xmx2G   -Dfoo=bar "), strings(&["-Xmx2G", "-Dfoo=bar"]));
+        assert_eq!(split_args("\"a b\" 'c d' e\"f g\""), strings(&["a b", "c d", "ef g"]));
+        assert_eq!(split_args("'' \"\""), strings(&["", ""]));
+        assert_eq!(split_args("a\\ b \\\"c\\' \"d\\\"e\" 'f\\g'"), strings(&["a b", "\"c'", "d\"e", "f\\g"]));
+        assert_eq!(split_args("C:\\Games\\java.exe \\\\server"), strings(&["C:\\Games\\java.exe", "\\server"]));
+    }
+
+    #[test]
+    fn join_args_round_trips() {
+        let cases = [
+            strings(&["-Xmx2G", "-Dfoo=bar"]),
+            strings(&["", "a b", "tab\there"]),
+            strings(&["it's", "say \"hi\"", "both ' and \""]),
+            strings(&["C:\\Program Files\\Java\\bin\\java.exe", "C:\\Games"]),
+            strings(&["ends with\\", "trailing\\", "\\\\double", "\\\"", "\\'"]),
+        ];
+        for args in cases.iter() {
+            assert_eq!(&split_args(&join_args(args)), args, "joined as {0}", join_args(args));
+        }
+    }
+
+    #[test]
+    fn batch_quote_escapes_for_cmd_and_java() {
+        assert_eq!(batch_quote("C:\\Games\\java.exe"), "C:\\Games\\java.exe");
+        assert_eq!(batch_quote("C:\\Program Files\\"), "\"C:\\Program Files\\\\\"");
+        assert_eq!(batch_quote("100% & more"), "\"100%% & more\"");
+        assert_eq!(batch_quote("say \\\"hi\""), "\"say \\\\\\\"hi\\\"\"");
+        assert_eq!(batch_quote(""), "\"\"");
+    }
+
+    #[test]
+    fn join_args_only_quotes_when_needed() {
+        assert_eq!(join_args(&strings(&["-Xmx2G", "C:\\Games"])), "-Xmx2G C:\\Games");
+        assert_eq!(join_args(&strings(&["a b", "it's", ""])), "\"a b\" \"it's\" \"\"");
+        assert_eq!(join_args(&strings(&["say \"hi\""])), "\"say \\\"hi\\\"\"");
+    }
+}